@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use seed15::phrase::{seed_phrase_to_seed, seed_phrase_to_seed_strict, seed_to_seed_phrase};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(phrase) = std::str::from_utf8(data) {
+        // Any phrase that parses must re-encode to a phrase that parses to the same seed.
+        if let Ok(seed) = seed_phrase_to_seed(phrase) {
+            assert_eq!(seed_phrase_to_seed(&seed_to_seed_phrase(seed)).unwrap(), seed);
+        }
+        let _ = seed_phrase_to_seed_strict(phrase);
+    }
+});
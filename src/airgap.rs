@@ -0,0 +1,449 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! airgap contains a request and response format for signing with a seed that lives on an offline
+//! machine. The online machine builds a SigningRequest and transfers its encoding to the offline
+//! machine, for example with qr_sequence_encode. The offline machine shows the request to the user,
+//! signs it with process_signing_request, and sends back the encoded SigningResponse. The online
+//! machine then checks the response with verify_signing_response.
+//!
+//! Both messages are encoded as canonical CBOR maps with small integer keys in ascending order,
+//! definite lengths, and minimal integer encodings. Decoding rejects any other encoding, so every
+//! message has exactly one valid encoding and the request hash is well defined.
+//!
+//! The signature covers the whole encoded request, not just the payload hash, so a response can't
+//! be replayed for a request with a different key, description, or version.
+
+use anyhow::{bail, Error, Result};
+use ed25519_dalek::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+
+use crate::derive::device_seed;
+use crate::keypair::{keypair_from_seed_zeroizing, sign_in_domain, verify_in_domain, Domain};
+use crate::Seed;
+
+/// SIGNING_REQUEST_VERSION is the only request version that process_signing_request accepts.
+pub const SIGNING_REQUEST_VERSION: u64 = 1;
+
+/// AIRGAP_DOMAIN is the signing domain for air-gapped signing requests.
+const AIRGAP_DOMAIN: Domain = Domain::new("seed15 airgap signing");
+
+const CBOR_UINT: u8 = 0;
+const CBOR_BYTES: u8 = 2;
+const CBOR_TEXT: u8 = 3;
+const CBOR_MAP: u8 = 5;
+
+/// SigningRequest asks the offline machine to sign a payload hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SigningRequest {
+    /// version is the format version, currently SIGNING_REQUEST_VERSION.
+    pub version: u64,
+    /// context selects the key. None uses the keypair of the seed, and Some(name) uses the
+    /// keypair of device_seed(seed, name).
+    pub context: Option<String>,
+    /// payload_hash is the hash of the data to sign, computed by the online machine.
+    pub payload_hash: [u8; 32],
+    /// human_description is shown to the user before they approve the request.
+    pub human_description: String,
+}
+
+/// SigningResponse carries the signature for a SigningRequest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SigningResponse {
+    /// signature signs the encoded request in the airgap signing domain.
+    pub signature: Signature,
+    /// public_key is the key that made the signature.
+    pub public_key: PublicKey,
+    /// request_hash is the SHA-256 of the encoded request.
+    pub request_hash: [u8; 32],
+}
+
+/// put_head appends a CBOR head with the shortest encoding of 'value'.
+fn put_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= 0xff {
+        out.extend_from_slice(&[major | 24, value as u8]);
+    } else if value <= 0xffff {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= 0xffff_ffff {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// put_bytes appends a CBOR byte string.
+fn put_bytes(out: &mut Vec<u8>, major: u8, bytes: &[u8]) {
+    put_head(out, major, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// CborReader decodes the canonical CBOR subset used by the airgap messages.
+struct CborReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    /// take removes 'n' bytes from the front of the input.
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < n {
+            bail!("message is truncated");
+        }
+        let (front, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(front)
+    }
+
+    /// head reads a CBOR head of the expected major type, rejecting non-minimal encodings.
+    fn head(&mut self, major: u8) -> Result<u64, Error> {
+        let initial = self.take(1)?[0];
+        if initial >> 5 != major {
+            bail!(
+                "expected CBOR major type {} but got {}",
+                major,
+                initial >> 5
+            );
+        }
+        let (value, minimum) = match initial & 0x1f {
+            n @ 0..=23 => (n as u64, 0),
+            24 => (self.take(1)?[0] as u64, 24),
+            25 => (u16::from_be_bytes(self.take(2)?.try_into()?) as u64, 0x100),
+            26 => (
+                u32::from_be_bytes(self.take(4)?.try_into()?) as u64,
+                0x1_0000,
+            ),
+            27 => (u64::from_be_bytes(self.take(8)?.try_into()?), 0x1_0000_0000),
+            _ => bail!("indefinite and reserved CBOR lengths are not supported"),
+        };
+        if value < minimum {
+            bail!("CBOR value {} is not minimally encoded", value);
+        }
+        Ok(value)
+    }
+
+    /// bytes reads a byte or text string of the expected major type.
+    fn bytes(&mut self, major: u8) -> Result<&'a [u8], Error> {
+        let len = self.head(major)?;
+        if len > self.data.len() as u64 {
+            bail!("message is truncated");
+        }
+        self.take(len as usize)
+    }
+
+    /// fixed reads a byte string of exactly N bytes.
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self.bytes(CBOR_BYTES)?;
+        match bytes.try_into() {
+            Ok(fixed) => Ok(fixed),
+            Err(_) => bail!("expected {} bytes but got {}", N, bytes.len()),
+        }
+    }
+
+    /// text reads a text string.
+    fn text(&mut self) -> Result<String, Error> {
+        Ok(std::str::from_utf8(self.bytes(CBOR_TEXT)?)?.to_string())
+    }
+
+    /// map reads the head of a map and returns its number of entries. next_key must be called
+    /// once per entry.
+    fn map(&mut self) -> Result<u64, Error> {
+        self.head(CBOR_MAP)
+    }
+
+    /// next_key reads a map key, which must be larger than 'prev'.
+    fn next_key(&mut self, prev: &mut u64) -> Result<u64, Error> {
+        let key = self.head(CBOR_UINT)?;
+        if key <= *prev {
+            bail!("CBOR map keys are not in ascending order");
+        }
+        *prev = key;
+        Ok(key)
+    }
+
+    /// finish checks that the whole message was consumed.
+    fn finish(&self) -> Result<(), Error> {
+        if !self.data.is_empty() {
+            bail!("message has {} trailing bytes", self.data.len());
+        }
+        Ok(())
+    }
+}
+
+impl SigningRequest {
+    /// encode returns the canonical CBOR encoding of the request.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_head(&mut out, CBOR_MAP, 3 + self.context.is_some() as u64);
+        put_head(&mut out, CBOR_UINT, 1);
+        put_head(&mut out, CBOR_UINT, self.version);
+        if let Some(context) = &self.context {
+            put_head(&mut out, CBOR_UINT, 2);
+            put_bytes(&mut out, CBOR_TEXT, context.as_bytes());
+        }
+        put_head(&mut out, CBOR_UINT, 3);
+        put_bytes(&mut out, CBOR_BYTES, &self.payload_hash);
+        put_head(&mut out, CBOR_UINT, 4);
+        put_bytes(&mut out, CBOR_TEXT, self.human_description.as_bytes());
+        out
+    }
+
+    /// decode parses a request produced by encode.
+    pub fn decode(data: &[u8]) -> Result<SigningRequest, Error> {
+        let mut reader = CborReader { data };
+        let entries = reader.map()?;
+        let (mut version, mut context, mut payload_hash, mut human_description) =
+            (None, None, None, None);
+        let mut prev = 0;
+        for _ in 0..entries {
+            match reader.next_key(&mut prev)? {
+                1 => version = Some(reader.head(CBOR_UINT)?),
+                2 => context = Some(reader.text()?),
+                3 => payload_hash = Some(reader.fixed::<32>()?),
+                4 => human_description = Some(reader.text()?),
+                key => bail!("unknown signing request field {}", key),
+            }
+        }
+        reader.finish()?;
+        match (version, payload_hash, human_description) {
+            (Some(version), Some(payload_hash), Some(human_description)) => Ok(SigningRequest {
+                version,
+                context,
+                payload_hash,
+                human_description,
+            }),
+            _ => bail!("signing request is missing a required field"),
+        }
+    }
+
+    /// hash returns the SHA-256 of the encoded request.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&Sha256::digest(self.encode()));
+        hash
+    }
+}
+
+impl SigningResponse {
+    /// encode returns the canonical CBOR encoding of the response.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_head(&mut out, CBOR_MAP, 3);
+        put_head(&mut out, CBOR_UINT, 1);
+        put_bytes(&mut out, CBOR_BYTES, &self.signature.to_bytes());
+        put_head(&mut out, CBOR_UINT, 2);
+        put_bytes(&mut out, CBOR_BYTES, self.public_key.as_bytes());
+        put_head(&mut out, CBOR_UINT, 3);
+        put_bytes(&mut out, CBOR_BYTES, &self.request_hash);
+        out
+    }
+
+    /// decode parses a response produced by encode.
+    pub fn decode(data: &[u8]) -> Result<SigningResponse, Error> {
+        let mut reader = CborReader { data };
+        if reader.map()? != 3 {
+            bail!("signing response must have exactly 3 fields");
+        }
+        let mut prev = 0;
+        let mut fields = Vec::with_capacity(3);
+        for expected in 1..=3 {
+            if reader.next_key(&mut prev)? != expected {
+                bail!("signing response is missing field {}", expected);
+            }
+            fields.push(reader.bytes(CBOR_BYTES)?);
+        }
+        reader.finish()?;
+        let request_hash = match fields[2].try_into() {
+            Ok(hash) => hash,
+            Err(_) => bail!("request hash must be 32 bytes"),
+        };
+        Ok(SigningResponse {
+            signature: Signature::from_bytes(fields[0])?,
+            public_key: PublicKey::from_bytes(fields[1])?,
+            request_hash,
+        })
+    }
+}
+
+/// process_signing_request signs a request on the offline machine. 'approve' is called with the
+/// request, and should show the description and key context to the user and return whether they
+/// approved it. The request is refused if it has an unsupported version or is not approved.
+pub fn process_signing_request(
+    seed: Seed,
+    request: &SigningRequest,
+    approve: impl Fn(&SigningRequest) -> bool,
+) -> Result<SigningResponse, Error> {
+    if request.version != SIGNING_REQUEST_VERSION {
+        bail!("unsupported signing request version {}", request.version);
+    }
+    if !approve(request) {
+        bail!("signing request was not approved");
+    }
+    let key_seed = match &request.context {
+        Some(context) => device_seed(seed, context),
+        None => seed,
+    };
+    Ok(SigningResponse {
+        signature: sign_in_domain(key_seed, AIRGAP_DOMAIN, &request.encode()),
+        public_key: keypair_from_seed_zeroizing(key_seed).public,
+        request_hash: request.hash(),
+    })
+}
+
+/// verify_signing_response checks on the online machine that a response answers exactly this
+/// request and was signed by 'public_key', which the online machine should already know for the
+/// request's context. Checking the key the response claims would prove nothing.
+pub fn verify_signing_response(
+    request: &SigningRequest,
+    response: &SigningResponse,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    if response.request_hash != request.hash() {
+        bail!("response is for a different request");
+    }
+    if response.public_key != *public_key {
+        bail!("response was signed by an unexpected key");
+    }
+    verify_in_domain(
+        public_key,
+        AIRGAP_DOMAIN,
+        &request.encode(),
+        &response.signature,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair_from_seed;
+    use crate::qr::{qr_sequence_encode, DecodeProgress, QrSequenceDecoder};
+    use crate::random_seed;
+
+    // request returns a request for the given context.
+    fn request(context: Option<&str>) -> SigningRequest {
+        SigningRequest {
+            version: SIGNING_REQUEST_VERSION,
+            context: context.map(|c| c.to_string()),
+            payload_hash: Sha256::digest(b"transaction").into(),
+            human_description: "send 5 coins to bob".to_string(),
+        }
+    }
+
+    #[test]
+    // Sign a request carried over a QR sequence and verify the response.
+    fn check_signing_round_trip() {
+        let seed = random_seed();
+        let req = request(Some("cold"));
+        let encoded = req.encode();
+        assert_eq!(SigningRequest::decode(&encoded).unwrap(), req);
+        let root = request(None);
+        assert_eq!(SigningRequest::decode(&root.encode()).unwrap(), root);
+
+        // Transfer the request with a QR sequence.
+        let mut decoder = QrSequenceDecoder::new();
+        let mut received = None;
+        for chunk in qr_sequence_encode(&encoded, 16) {
+            if let DecodeProgress::Complete(data) = decoder.push_chunk(&chunk).unwrap() {
+                received = Some(SigningRequest::decode(&data).unwrap());
+            }
+        }
+        let received = received.unwrap();
+
+        let response = process_signing_request(seed, &received, |r| r == &req).unwrap();
+        let response = SigningResponse::decode(&response.encode()).unwrap();
+        let cold = keypair_from_seed(device_seed(seed, "cold")).public;
+        verify_signing_response(&req, &response, &cold).unwrap();
+
+        // Unapproved requests and unknown versions are refused.
+        process_signing_request(seed, &req, |_| false).unwrap_err();
+        let mut future = req.clone();
+        future.version = 2;
+        process_signing_request(seed, &future, |_| true).unwrap_err();
+
+        // Root requests use the seed's own keypair.
+        let response = process_signing_request(seed, &root, |_| true).unwrap();
+        verify_signing_response(&root, &response, &keypair_from_seed(seed).public).unwrap();
+    }
+
+    #[test]
+    // Responses must not verify for any other request or key.
+    fn check_signing_response_binding() {
+        let seed = random_seed();
+        let req = request(Some("cold"));
+        let cold = keypair_from_seed(device_seed(seed, "cold")).public;
+        let response = process_signing_request(seed, &req, |_| true).unwrap();
+
+        // A response for a different context or description is rejected.
+        let warm = request(Some("warm"));
+        verify_signing_response(&warm, &response, &cold).unwrap_err();
+        let mut altered = req.clone();
+        altered.human_description = "send 5 coins to eve".to_string();
+        verify_signing_response(&altered, &response, &cold).unwrap_err();
+
+        // A response signed by the wrong context's key is rejected, even for the right request.
+        let wrong = process_signing_request(seed, &warm, |_| true).unwrap();
+        let warm_key = keypair_from_seed(device_seed(seed, "warm")).public;
+        let mut forged = wrong.clone();
+        forged.request_hash = req.hash();
+        verify_signing_response(&req, &forged, &cold).unwrap_err();
+        forged.public_key = cold;
+        verify_signing_response(&req, &forged, &cold).unwrap_err();
+        verify_signing_response(&warm, &wrong, &warm_key).unwrap();
+
+        // A tampered request hash is rejected.
+        let mut tampered = response.clone();
+        tampered.request_hash[0] ^= 1;
+        verify_signing_response(&req, &tampered, &cold).unwrap_err();
+        let mut encoded = response.encode();
+        let last = encoded.len() - 1;
+        encoded[last] ^= 1;
+        let decoded = SigningResponse::decode(&encoded).unwrap();
+        verify_signing_response(&req, &decoded, &cold).unwrap_err();
+    }
+
+    #[test]
+    // Only the canonical encoding decodes.
+    fn check_canonical_cbor() {
+        let req = request(Some("cold"));
+        let encoded = req.encode();
+        assert_eq!(encoded[..4], [0xa4, 0x01, 0x01, 0x02]);
+
+        // Trailing bytes and truncation.
+        let mut long = encoded.clone();
+        long.push(0);
+        SigningRequest::decode(&long).unwrap_err();
+        for len in 0..encoded.len() {
+            SigningRequest::decode(&encoded[..len]).unwrap_err();
+        }
+
+        // A non-minimal version, and keys out of order.
+        let mut padded = vec![0xa4, 0x01, 0x18, 0x01];
+        padded.extend_from_slice(&encoded[3..]);
+        SigningRequest::decode(&padded).unwrap_err();
+        let mut unordered = req.clone();
+        unordered.context = None;
+        let mut swapped = vec![0xa3];
+        let mut tail = unordered.encode()[3..].to_vec();
+        swapped.extend_from_slice(&tail.split_off(35));
+        swapped.extend_from_slice(&tail);
+        swapped.extend_from_slice(&[0x01, 0x01]);
+        SigningRequest::decode(&swapped).unwrap_err();
+
+        // Large integers and lengths use the shortest head.
+        let mut out = Vec::new();
+        for value in [23, 24, 255, 256, 65535, 65536, u32::MAX as u64 + 1] {
+            out.clear();
+            put_head(&mut out, CBOR_UINT, value);
+            let mut reader = CborReader { data: &out };
+            assert_eq!(reader.head(CBOR_UINT).unwrap(), value);
+            reader.finish().unwrap();
+        }
+        SigningResponse::decode(&encoded).unwrap_err();
+    }
+}
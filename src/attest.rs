@@ -0,0 +1,293 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! attest contains short lived attestations that prove control of a seed without revealing it,
+//! for example to a support agent. The verifier sends a fresh challenge, the user signs it with
+//! attest, and the verifier checks the result with verify_attestation. An attestation renders as
+//! a single line of base64 so it can be pasted into a chat.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{PublicKey, Signature};
+
+use crate::keypair::{keypair_from_seed_zeroizing, sign_in_domain, verify_in_domain, Domain};
+use crate::Seed;
+
+/// ATTESTATION_DOMAIN is the signing domain for attestations.
+const ATTESTATION_DOMAIN: Domain = Domain::new("seed15 attestation");
+
+/// ATTESTATION_VERSION is the first byte of an encoded attestation.
+const ATTESTATION_VERSION: u8 = 1;
+
+/// HEADER_LEN is the length of the fixed size fields of an encoded attestation: the version, the
+/// public key, the two timestamps, and the signature.
+const HEADER_LEN: usize = 1 + 32 + 8 + 8 + 64;
+
+/// Attestation is a signed statement that the holder of a seed answered a challenge. Times are
+/// whole seconds since the unix epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    /// public_key is the public key of the seed.
+    pub public_key: PublicKey,
+    /// challenge is the challenge that was signed.
+    pub challenge: Vec<u8>,
+    /// issued_at is when the attestation was made.
+    pub issued_at: u64,
+    /// expires_at is the first second at which the attestation is no longer valid.
+    pub expires_at: u64,
+    /// signature signs every other field in the attestation domain.
+    pub signature: Signature,
+}
+
+/// AttestationError describes why an attestation was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttestationError {
+    /// Malformed indicates that an encoded attestation could not be decoded.
+    Malformed,
+    /// Expired indicates that the attestation is past its expiry.
+    Expired {
+        /// expires_at is when the attestation expired, in seconds since the unix epoch.
+        expires_at: u64,
+    },
+    /// WrongChallenge indicates that the attestation answers a different challenge, for example
+    /// because it is being replayed.
+    WrongChallenge,
+    /// BadSignature indicates that the signature does not match the other fields.
+    BadSignature,
+}
+
+impl fmt::Display for AttestationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttestationError::Malformed => write!(f, "attestation is malformed"),
+            AttestationError::Expired { expires_at } => write!(
+                f,
+                "attestation expired at {} seconds after the unix epoch",
+                expires_at
+            ),
+            AttestationError::WrongChallenge => {
+                write!(f, "attestation answers a different challenge")
+            }
+            AttestationError::BadSignature => write!(f, "attestation signature is not valid"),
+        }
+    }
+}
+
+impl std::error::Error for AttestationError {}
+
+/// signed_data returns the bytes covered by the signature of an attestation. The challenge has a
+/// variable length, so it goes last to keep the encoding unambiguous.
+fn signed_data(
+    public_key: &PublicKey,
+    issued_at: u64,
+    expires_at: u64,
+    challenge: &[u8],
+) -> Vec<u8> {
+    let mut data = Vec::with_capacity(48 + challenge.len());
+    data.extend_from_slice(public_key.as_bytes());
+    data.extend_from_slice(&issued_at.to_le_bytes());
+    data.extend_from_slice(&expires_at.to_le_bytes());
+    data.extend_from_slice(challenge);
+    data
+}
+
+impl Attestation {
+    /// to_base64 renders the attestation as a single line of base64.
+    pub fn to_base64(&self) -> String {
+        let mut encoded = Vec::with_capacity(HEADER_LEN + self.challenge.len());
+        encoded.push(ATTESTATION_VERSION);
+        encoded.extend_from_slice(self.public_key.as_bytes());
+        encoded.extend_from_slice(&self.issued_at.to_le_bytes());
+        encoded.extend_from_slice(&self.expires_at.to_le_bytes());
+        encoded.extend_from_slice(&self.signature.to_bytes());
+        encoded.extend_from_slice(&self.challenge);
+        STANDARD.encode(encoded)
+    }
+
+    /// from_base64 decodes an attestation rendered by to_base64. Surrounding whitespace is
+    /// ignored. The attestation still has to be checked with verify_attestation.
+    pub fn from_base64(text: &str) -> Result<Attestation, AttestationError> {
+        let encoded = match STANDARD.decode(text.trim()) {
+            Ok(encoded) => encoded,
+            Err(_) => return Err(AttestationError::Malformed),
+        };
+        if encoded.len() < HEADER_LEN || encoded[0] != ATTESTATION_VERSION {
+            return Err(AttestationError::Malformed);
+        }
+        let u64_at = |at: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&encoded[at..at + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let public_key = match PublicKey::from_bytes(&encoded[1..33]) {
+            Ok(public_key) => public_key,
+            Err(_) => return Err(AttestationError::Malformed),
+        };
+        let signature = match Signature::from_bytes(&encoded[49..HEADER_LEN]) {
+            Ok(signature) => signature,
+            Err(_) => return Err(AttestationError::Malformed),
+        };
+        Ok(Attestation {
+            public_key,
+            challenge: encoded[HEADER_LEN..].to_vec(),
+            issued_at: u64_at(33),
+            expires_at: u64_at(41),
+            signature,
+        })
+    }
+}
+
+/// unix_seconds returns a time as whole seconds since the unix epoch, treating earlier times as
+/// the epoch itself.
+fn unix_seconds(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// attest signs 'challenge' with the keypair of the seed, producing an attestation that is valid
+/// for 'ttl' from now, rounded down to whole seconds. The challenge should be a fresh random value
+/// chosen by the verifier, so that old attestations can't be replayed.
+pub fn attest(seed: Seed, challenge: &[u8], ttl: Duration) -> Attestation {
+    let public_key = keypair_from_seed_zeroizing(seed).public;
+    let issued_at = unix_seconds(SystemTime::now());
+    let expires_at = issued_at.saturating_add(ttl.as_secs());
+    let data = signed_data(&public_key, issued_at, expires_at, challenge);
+    Attestation {
+        public_key,
+        challenge: challenge.to_vec(),
+        issued_at,
+        expires_at,
+        signature: sign_in_domain(seed, ATTESTATION_DOMAIN, &data),
+    }
+}
+
+/// verify_attestation checks that an attestation answers 'expected_challenge', has not expired at
+/// 'now', and is correctly signed, and returns the public key that signed it. The caller decides
+/// whether that public key is the one it expected. An attestation is valid up to, but not
+/// including, its expires_at second.
+pub fn verify_attestation(
+    att: &Attestation,
+    expected_challenge: &[u8],
+    now: SystemTime,
+) -> Result<PublicKey, AttestationError> {
+    if att.challenge != expected_challenge {
+        return Err(AttestationError::WrongChallenge);
+    }
+    if unix_seconds(now) >= att.expires_at {
+        return Err(AttestationError::Expired {
+            expires_at: att.expires_at,
+        });
+    }
+    match verify_in_domain(
+        &att.public_key,
+        ATTESTATION_DOMAIN,
+        &signed_data(
+            &att.public_key,
+            att.issued_at,
+            att.expires_at,
+            &att.challenge,
+        ),
+        &att.signature,
+    ) {
+        Ok(()) => Ok(att.public_key),
+        Err(_) => Err(AttestationError::BadSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair_from_seed;
+    use crate::random_seed;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    // A fresh attestation verifies, and survives the base64 rendering.
+    fn check_attestation() {
+        let seed = random_seed();
+        let att = attest(seed, b"support ticket 4411", 10 * MINUTE);
+        assert_eq!(att.expires_at - att.issued_at, 600);
+        let now = SystemTime::now();
+        let public = verify_attestation(&att, b"support ticket 4411", now).unwrap();
+        assert_eq!(public, keypair_from_seed(seed).public);
+
+        let line = att.to_base64();
+        assert!(!line.contains('\n'));
+        let decoded = Attestation::from_base64(&format!(" {}\n", line)).unwrap();
+        assert_eq!(decoded, att);
+        verify_attestation(&decoded, b"support ticket 4411", now).unwrap();
+
+        // An empty challenge is allowed.
+        let att = attest(seed, b"", MINUTE);
+        verify_attestation(
+            &Attestation::from_base64(&att.to_base64()).unwrap(),
+            b"",
+            now,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    // Expired, replayed, tampered, and malformed attestations are rejected with the right error.
+    fn check_attestation_errors() {
+        let seed = random_seed();
+        let att = attest(seed, b"nonce 1", MINUTE);
+        let expires = UNIX_EPOCH + Duration::from_secs(att.expires_at);
+        let expired = AttestationError::Expired {
+            expires_at: att.expires_at,
+        };
+        verify_attestation(&att, b"nonce 1", expires - Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            verify_attestation(&att, b"nonce 1", expires),
+            Err(expired.clone())
+        );
+        assert_eq!(
+            verify_attestation(&att, b"nonce 1", expires + MINUTE),
+            Err(expired)
+        );
+
+        // Replaying the attestation for a different challenge.
+        let now = SystemTime::now();
+        assert_eq!(
+            verify_attestation(&att, b"nonce 2", now),
+            Err(AttestationError::WrongChallenge)
+        );
+
+        // Tampering with any signed field.
+        let mut tampered = att.clone();
+        tampered.expires_at += 3600;
+        assert_eq!(
+            verify_attestation(&tampered, b"nonce 1", now),
+            Err(AttestationError::BadSignature)
+        );
+        let mut tampered = att.clone();
+        tampered.challenge = b"nonce 2".to_vec();
+        assert_eq!(
+            verify_attestation(&tampered, b"nonce 2", now),
+            Err(AttestationError::BadSignature)
+        );
+        let mut tampered = att.clone();
+        tampered.public_key = keypair_from_seed(random_seed()).public;
+        assert_eq!(
+            verify_attestation(&tampered, b"nonce 1", now),
+            Err(AttestationError::BadSignature)
+        );
+
+        // Malformed renderings.
+        let line = att.to_base64();
+        let mut encoded = STANDARD.decode(&line).unwrap();
+        encoded[0] = 2;
+        for text in ["", "not base64!", &line[..20], &STANDARD.encode(&encoded)] {
+            assert_eq!(
+                Attestation::from_base64(text),
+                Err(AttestationError::Malformed)
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! audit contains a plain-text trace of the conversion from a seed phrase to a seed, so that an
+//! auditor can check each step by hand.
+
+use std::fmt::Write;
+
+use anyhow::{Error, Result};
+use dictionary_1024::DICTIONARY;
+use sha2::{Digest, Sha256};
+
+use crate::phrase::{
+    checksum_word_indices, seed_phrase_to_seed, seed_to_seed_phrase, SEED_ENTROPY_WORDS,
+};
+
+/// audit_trace validates a seed phrase and returns a trace of how it maps to its seed: the index
+/// of every word in decimal and binary, the 128 entropy bits with byte boundaries marked, the seed
+/// in hex, the sha256 of the seed, and the 20 checksum bits split back into the two checksum
+/// words. Words are numbered from zero and are shown in their full dictionary spelling.
+///
+/// The format is fixed so that traces stay comparable across versions of this crate; any change
+/// to it is a breaking change.
+///
+/// The trace contains the seed in the clear. It is intended for checking test vectors on an
+/// air-gapped machine and must never be logged or produced for a real seed.
+pub fn audit_trace(phrase: &str) -> Result<String, Error> {
+    let seed = seed_phrase_to_seed(phrase)?;
+    let canonical = seed_to_seed_phrase(seed);
+    let words: Vec<&str> = canonical.split(' ').collect();
+    let index = |word: &str| DICTIONARY.iter().position(|w| *w == word).unwrap();
+
+    // Writing to a String can't fail, so the results of writeln! are ignored throughout.
+    let mut out = String::new();
+    let _ = writeln!(out, "seed15 audit trace v1");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "entropy words");
+    let mut bits = String::new();
+    for (i, word) in words[..SEED_ENTROPY_WORDS].iter().enumerate() {
+        let n = index(word);
+        let note = if i == SEED_ENTROPY_WORDS - 1 {
+            "  (low 8 bits used)"
+        } else {
+            ""
+        };
+        let _ = writeln!(out, "{:>2} {:<6} {:>4} {:010b}{}", i, word, n, n, note);
+        if i == SEED_ENTROPY_WORDS - 1 {
+            let _ = write!(bits, "{:08b}", n);
+        } else {
+            let _ = write!(bits, "{:010b}", n);
+        }
+    }
+    let _ = writeln!(out);
+
+    // Print the entropy bits as two rows of eight bytes.
+    let _ = writeln!(out, "entropy bits");
+    let bytes: Vec<&str> = (0..16).map(|i| &bits[i * 8..i * 8 + 8]).collect();
+    let _ = writeln!(out, "{}", bytes[..8].join("|"));
+    let _ = writeln!(out, "{}", bytes[8..].join("|"));
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "seed");
+    let _ = writeln!(out, "{}", hex(&seed));
+    let _ = writeln!(out);
+
+    let hash = Sha256::digest(seed);
+    let _ = writeln!(out, "sha256(seed)");
+    let _ = writeln!(out, "{}", hex(&hash));
+    let _ = writeln!(out);
+
+    let (c1, c2) = checksum_word_indices(seed);
+    let _ = writeln!(out, "checksum bits");
+    let _ = writeln!(out, "{:010b}|{:010b}", c1, c2);
+    for (i, n) in [(SEED_ENTROPY_WORDS, c1), (SEED_ENTROPY_WORDS + 1, c2)] {
+        let _ = writeln!(out, "{:>2} {:<6} {:>4} {:010b}", i, DICTIONARY[n], n, n);
+    }
+    Ok(out)
+}
+
+/// hex returns the lowercase hex encoding of the bytes.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = "\
+seed15 audit trace v1\n\
+\n\
+entropy words\n\
+\x200 abbey     0 0000000000\n\
+\x201 afar     16 0000010000\n\
+\x202 boss    128 0010000000\n\
+\x203 rope    772 1100000100\n\
+\x204 afoot    20 0000010100\n\
+\x205 bad      96 0001100000\n\
+\x206 issue   450 0111000010\n\
+\x207 acumen    9 0000001001\n\
+\x208 also     40 0000101000\n\
+\x209 cog     176 0010110000\n\
+10 room    771 1100000011\n\
+11 emit    270 0100001110\n\
+12 aerial   15 0000001111  (low 8 bits used)\n\
+\n\
+entropy bits\n\
+00000000|00000001|00000010|00000011|00000100|00000101|00000110|00000111\n\
+00001000|00001001|00001010|00001011|00001100|00001101|00001110|00001111\n\
+\n\
+seed\n\
+000102030405060708090a0b0c0d0e0f\n\
+\n\
+sha256(seed)\n\
+be45cb2605bf36bebde684841a28f0fd43c69850a3dce5fedba69928ee3a8991\n\
+\n\
+checksum bits\n\
+1011111001|0001011100\n\
+13 riot    761 1011111001\n\
+14 aztec    92 0001011100\n";
+
+    #[test]
+    // The trace format must never change, check it against a snapshot.
+    fn check_audit_trace() {
+        let seed = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let phrase = seed_to_seed_phrase(seed);
+        let trace = audit_trace(&phrase).unwrap();
+        assert_eq!(trace, SNAPSHOT);
+
+        // Abbreviated words do not change the trace.
+        let abbreviated: Vec<&str> = phrase.split(' ').map(|w| &w[..3]).collect();
+        assert_eq!(audit_trace(&abbreviated.join(" ")).unwrap(), trace);
+        audit_trace("abbey").unwrap_err();
+    }
+}
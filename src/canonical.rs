@@ -0,0 +1,272 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! canonical contains a validator for the exact textual form of a seed phrase. The parser in the
+//! phrase module is lenient about word spelling, which is helpful for users typing phrases but not
+//! for archival storage, where a phrase should be stored in exactly one form.
+
+use std::fmt;
+
+use anyhow::{Error, Result};
+use dictionary_1024::DICTIONARY;
+
+use crate::phrase::{
+    checksum_word_indices, entropy_indices_to_seed, seed_phrase_to_seed, SEED_CHECKSUM_WORDS,
+    SEED_ENTROPY_WORDS,
+};
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// CanonicalViolationKind describes the way in which a phrase is not canonical.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanonicalViolationKind {
+    /// Spacing indicates leading or trailing whitespace, repeated spaces, or whitespace other
+    /// than a single ascii space between words.
+    Spacing,
+    /// WordCount indicates that the phrase does not have exactly 15 words.
+    WordCount,
+    /// Case indicates that a word contains uppercase letters.
+    Case,
+    /// UnknownWord indicates that a word does not match any dictionary word, even by prefix.
+    UnknownWord,
+    /// PrefixOnlyWord indicates that a word matches a dictionary word by prefix but is not
+    /// spelled exactly like it.
+    PrefixOnlyWord,
+    /// FinalWordOutOfRange indicates that the 13th word is not one of the first 256 dictionary
+    /// words.
+    FinalWordOutOfRange,
+    /// Checksum indicates that a checksum word does not match the seed.
+    Checksum,
+}
+
+/// CanonicalViolation describes the first violation found in a phrase. For Spacing violations,
+/// 'position' is the index of the word that follows the bad whitespace (15 for trailing
+/// whitespace). For WordCount violations it is the number of words found. For all other
+/// violations it is the index of the offending word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CanonicalViolation {
+    /// position identifies where the violation occurred.
+    pub position: usize,
+    /// kind describes the violation.
+    pub kind: CanonicalViolationKind,
+}
+
+impl fmt::Display for CanonicalViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CanonicalViolationKind::Spacing => {
+                write!(f, "non-canonical whitespace before word {}", self.position)
+            }
+            CanonicalViolationKind::WordCount => write!(
+                f,
+                "expecting {} words but got {} words",
+                PHRASE_WORDS, self.position
+            ),
+            CanonicalViolationKind::Case => write!(f, "word {} is not lowercase", self.position),
+            CanonicalViolationKind::UnknownWord => {
+                write!(f, "word {} is not in the dictionary", self.position)
+            }
+            CanonicalViolationKind::PrefixOnlyWord => write!(
+                f,
+                "word {} is not spelled exactly as its dictionary word",
+                self.position
+            ),
+            CanonicalViolationKind::FinalWordOutOfRange => {
+                write!(f, "word {} cannot be the 13th word", self.position)
+            }
+            CanonicalViolationKind::Checksum => {
+                write!(f, "checksum word {} is incorrect", self.position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CanonicalViolation {}
+
+/// validate_canonical checks that a phrase is in canonical form: exactly 15 lowercase words
+/// separated by single spaces with no leading or trailing whitespace, every word spelled exactly
+/// as it appears in the dictionary, and a valid checksum. The first violation is returned.
+pub fn validate_canonical(phrase: &str) -> Result<(), CanonicalViolation> {
+    let violation = |position, kind| Err(CanonicalViolation { position, kind });
+
+    // Check the spacing before splitting, so that the word positions are meaningful.
+    let mut word = 0;
+    let mut prev_space = true;
+    for c in phrase.chars() {
+        if c == ' ' && !prev_space {
+            prev_space = true;
+            word += 1;
+        } else if c.is_whitespace() {
+            return violation(word, CanonicalViolationKind::Spacing);
+        } else {
+            prev_space = false;
+        }
+    }
+    if prev_space && !phrase.is_empty() {
+        return violation(word, CanonicalViolationKind::Spacing);
+    }
+
+    let words: Vec<&str> = phrase.split(' ').filter(|w| !w.is_empty()).collect();
+    if words.len() != PHRASE_WORDS {
+        return violation(words.len(), CanonicalViolationKind::WordCount);
+    }
+
+    // Check the spelling of every word.
+    let mut indices = [0usize; PHRASE_WORDS];
+    for (i, word) in words.iter().enumerate() {
+        if word.chars().any(|c| c.is_uppercase()) {
+            return violation(i, CanonicalViolationKind::Case);
+        }
+        if let Some(index) = DICTIONARY.iter().position(|w| w == word) {
+            indices[i] = index;
+            continue;
+        }
+        let prefix_match = word.len() >= 3
+            && word.is_char_boundary(3)
+            && DICTIONARY.iter().any(|w| w[..3] == word[..3]);
+        if prefix_match {
+            return violation(i, CanonicalViolationKind::PrefixOnlyWord);
+        }
+        return violation(i, CanonicalViolationKind::UnknownWord);
+    }
+
+    // Check the 13th word and the checksum.
+    let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+    entropy.copy_from_slice(&indices[..SEED_ENTROPY_WORDS]);
+    let seed = match entropy_indices_to_seed(&entropy) {
+        Ok(s) => s,
+        Err(_) => {
+            return violation(
+                SEED_ENTROPY_WORDS - 1,
+                CanonicalViolationKind::FinalWordOutOfRange,
+            )
+        }
+    };
+    let (c1, c2) = checksum_word_indices(seed);
+    if indices[SEED_ENTROPY_WORDS] != c1 {
+        return violation(SEED_ENTROPY_WORDS, CanonicalViolationKind::Checksum);
+    }
+    if indices[SEED_ENTROPY_WORDS + 1] != c2 {
+        return violation(SEED_ENTROPY_WORDS + 1, CanonicalViolationKind::Checksum);
+    }
+    Ok(())
+}
+
+/// canonicalize_checksum_words replaces the two checksum words of a valid phrase with their
+/// canonical dictionary spelling. The entropy words are left exactly as they were written, so a
+/// stored backup can have its checksum words normalized without touching anything else. An error
+/// is returned if the phrase is not valid.
+pub fn canonicalize_checksum_words(phrase: &str) -> Result<String, Error> {
+    let seed = seed_phrase_to_seed(phrase)?;
+    let (c1, c2) = checksum_word_indices(seed);
+    let mut words: Vec<&str> = phrase.split(' ').take(SEED_ENTROPY_WORDS).collect();
+    words.push(DICTIONARY[c1]);
+    words.push(DICTIONARY[c2]);
+    Ok(words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_to_seed_phrase;
+    use crate::random_seed;
+    use dictionary_1024::{index_of_word, word_at_index};
+
+    #[test]
+    // Check a canonical phrase and one example of each violation.
+    fn check_validate_canonical() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        validate_canonical(&phrase).unwrap();
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let with_word = |i: usize, w: &str| {
+            let mut words = words.clone();
+            words[i] = w;
+            words.join(" ")
+        };
+        let check = |input: &str, position, kind| {
+            assert_eq!(
+                validate_canonical(input),
+                Err(CanonicalViolation { position, kind }),
+                "{}",
+                input
+            );
+        };
+
+        // Spacing.
+        check(&format!(" {}", phrase), 0, CanonicalViolationKind::Spacing);
+        check(&format!("{} ", phrase), 15, CanonicalViolationKind::Spacing);
+        check(
+            &phrase.replacen(' ', "  ", 1),
+            1,
+            CanonicalViolationKind::Spacing,
+        );
+        check(
+            &phrase.replacen(' ', "\t", 1),
+            0,
+            CanonicalViolationKind::Spacing,
+        );
+
+        // Word count.
+        check(
+            &words[..14].join(" "),
+            14,
+            CanonicalViolationKind::WordCount,
+        );
+        check("", 0, CanonicalViolationKind::WordCount);
+
+        // Case.
+        let upper = words[4].to_uppercase();
+        check(&with_word(4, &upper), 4, CanonicalViolationKind::Case);
+
+        // Unknown and prefix-only words.
+        check(&with_word(2, "zzz"), 2, CanonicalViolationKind::UnknownWord);
+        let prefix = words[7][..3].to_string() + "q";
+        check(
+            &with_word(7, &prefix),
+            7,
+            CanonicalViolationKind::PrefixOnlyWord,
+        );
+
+        // 13th word out of range.
+        let index = index_of_word(words[12]).unwrap();
+        let out_of_range = word_at_index(index + 256);
+        check(
+            &with_word(12, &out_of_range),
+            12,
+            CanonicalViolationKind::FinalWordOutOfRange,
+        );
+
+        // Checksum.
+        let index = index_of_word(words[14]).unwrap();
+        let wrong = word_at_index((index + 1) % 1024);
+        check(&with_word(14, &wrong), 14, CanonicalViolationKind::Checksum);
+    }
+
+    #[test]
+    // Prefix-equivalent checksum words are replaced and the entropy words are kept.
+    fn check_canonicalize_checksum_words() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        assert_eq!(canonicalize_checksum_words(&phrase).unwrap(), phrase);
+
+        let mut words: Vec<String> = phrase.split(' ').map(|w| w.to_string()).collect();
+        words[0] = words[0][..3].to_string();
+        words[13] = format!("{}q", &words[13][..3]);
+        words[14] = words[14][..3].to_string();
+        let lenient = words.join(" ");
+        validate_canonical(&lenient).unwrap_err();
+
+        let canonical: Vec<&str> = phrase.split(' ').collect();
+        words[13] = canonical[13].to_string();
+        words[14] = canonical[14].to_string();
+        assert_eq!(
+            canonicalize_checksum_words(&lenient).unwrap(),
+            words.join(" ")
+        );
+
+        let wrong = format!("{} zzz", canonical[..14].join(" "));
+        canonicalize_checksum_words(&wrong).unwrap_err();
+        canonicalize_checksum_words("").unwrap_err();
+    }
+}
@@ -0,0 +1,142 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! confirm contains a helper for confirming a backup by having the user type their seed phrase
+//! twice. Only the seed from the first attempt is kept, never the text of either attempt.
+
+use anyhow::{bail, Error, Result};
+use zeroize::Zeroizing;
+
+use crate::dictionary::same_word;
+use crate::phrase::{
+    seed_phrase_to_seed, seed_to_seed_phrase, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS,
+};
+use crate::Seed;
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// DoubleEntryResult describes how the second attempt compares to the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DoubleEntryResult {
+    /// Match indicates that both attempts are the same seed phrase.
+    Match,
+    /// MismatchAtPositions lists the word positions where the second attempt differs from the
+    /// first. A second attempt with missing words is reported as a mismatch at each missing
+    /// position, and extra words are reported at their positions past the end of the phrase.
+    MismatchAtPositions(Vec<usize>),
+    /// FirstAttemptInvalid indicates that the first attempt was not a valid seed phrase, so there
+    /// is nothing to compare against.
+    FirstAttemptInvalid,
+}
+
+/// DoubleEntry holds the seed from the first attempt at typing a seed phrase.
+pub struct DoubleEntry {
+    seed: Option<Zeroizing<Seed>>,
+}
+
+impl DoubleEntry {
+    /// first records the first attempt. An error is returned if the attempt does not have 15
+    /// words, since the user has clearly not finished typing. An attempt with 15 words that fails
+    /// validation is accepted so that the problem can be reported together with the second
+    /// attempt; apps that would rather reject it immediately can call seed_phrase_to_seed first.
+    pub fn first(phrase: &str) -> Result<DoubleEntry, Error> {
+        let count = phrase.split_whitespace().count();
+        if count != PHRASE_WORDS {
+            bail!("expecting {} words but got {} words", PHRASE_WORDS, count);
+        }
+        let seed = seed_phrase_to_seed(&normalize(phrase))
+            .ok()
+            .map(Zeroizing::new);
+        Ok(DoubleEntry { seed })
+    }
+
+    /// second compares the second attempt against the first. The comparison ignores case and
+    /// extra whitespace, and like seed_phrase_to_seed only considers the dictionary prefix of
+    /// each word. The canonical words are re-derived from the stored seed and compared one at a
+    /// time, so the second attempt is never stored.
+    pub fn second(&self, phrase: &str) -> DoubleEntryResult {
+        let seed = match &self.seed {
+            Some(s) => s,
+            None => return DoubleEntryResult::FirstAttemptInvalid,
+        };
+        let expected = Zeroizing::new(seed_to_seed_phrase(**seed));
+        let mut expected_words = expected.split(' ');
+        let mut attempt_words = phrase.split_whitespace();
+        let mut mismatches = Vec::new();
+        for i in 0.. {
+            match (expected_words.next(), attempt_words.next()) {
+                (None, None) => break,
+                (Some(e), Some(a)) => {
+                    let a = Zeroizing::new(a.to_lowercase());
+                    if !same_word(e, &a) {
+                        mismatches.push(i);
+                    }
+                }
+                _ => mismatches.push(i),
+            }
+        }
+        if mismatches.is_empty() {
+            DoubleEntryResult::Match
+        } else {
+            DoubleEntryResult::MismatchAtPositions(mismatches)
+        }
+    }
+}
+
+/// normalize lowercases a phrase and joins its words with single spaces.
+fn normalize(phrase: &str) -> Zeroizing<String> {
+    let lower = Zeroizing::new(phrase.to_lowercase());
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    Zeroizing::new(words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_seed;
+
+    #[test]
+    // Check each outcome of a double entry.
+    fn check_double_entry() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let entry = DoubleEntry::first(&phrase).unwrap();
+        assert_eq!(entry.second(&phrase), DoubleEntryResult::Match);
+
+        // Case, whitespace, and prefix differences still match.
+        let shouty = format!("  {}\n", phrase.to_uppercase().replace(' ', "\t "));
+        assert_eq!(entry.second(&shouty), DoubleEntryResult::Match);
+        let prefixes: Vec<&str> = words.iter().map(|w| &w[..3]).collect();
+        assert_eq!(entry.second(&prefixes.join(" ")), DoubleEntryResult::Match);
+        let entry = DoubleEntry::first(&shouty).unwrap();
+        assert_eq!(entry.second(&phrase), DoubleEntryResult::Match);
+
+        // Mismatched, missing, and extra words.
+        let mut wrong = words.clone();
+        wrong[3] = "zzz";
+        wrong[14] = "caf\u{e9}";
+        assert_eq!(
+            entry.second(&wrong.join(" ")),
+            DoubleEntryResult::MismatchAtPositions(vec![3, 14])
+        );
+        assert_eq!(
+            entry.second(&words[..13].join(" ")),
+            DoubleEntryResult::MismatchAtPositions(vec![13, 14])
+        );
+        assert_eq!(
+            entry.second(&format!("{} abbey", phrase)),
+            DoubleEntryResult::MismatchAtPositions(vec![15])
+        );
+
+        // An invalid first attempt is reported on the second attempt, and an incomplete one is
+        // rejected immediately.
+        let entry = DoubleEntry::first(&wrong.join(" ")).unwrap();
+        assert_eq!(
+            entry.second(&phrase),
+            DoubleEntryResult::FirstAttemptInvalid
+        );
+        assert!(DoubleEntry::first(&words[..14].join(" ")).is_err());
+    }
+}
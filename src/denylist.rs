@@ -0,0 +1,49 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! denylist contains helpers for detecting seeds that are publicly known and therefore unsafe to
+//! use, such as the all-zero seed or seeds that appear in documentation and test vectors.
+
+use crate::Seed;
+
+/// DENYLISTED_SEEDS contains seeds that are widely known: trivial byte patterns and the seeds used
+/// as fixtures in this crate's tests.
+const DENYLISTED_SEEDS: [Seed; 6] = [
+    [0x00; 16],
+    [0xff; 16],
+    [0xaa; 16],
+    [0x55; 16],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [185, 46, 7, 1, 254, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+];
+
+/// is_denylisted_seed returns true if the seed is one of a small set of publicly known seeds. Any
+/// funds or keys protected by such a seed should be considered compromised.
+pub fn is_denylisted_seed(seed: Seed) -> bool {
+    DENYLISTED_SEEDS.contains(&seed)
+}
+
+/// is_denylisted_seed_with_denylist returns true if the seed is in the built-in denylist or in the
+/// provided list of additional seeds.
+pub fn is_denylisted_seed_with_denylist(seed: Seed, denylist: &[Seed]) -> bool {
+    is_denylisted_seed(seed) || denylist.contains(&seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_seed;
+
+    #[test]
+    // Check the built-in denylist and the extended denylist.
+    fn check_is_denylisted_seed() {
+        assert!(is_denylisted_seed([0u8; 16]));
+        let seed = random_seed();
+        assert!(!is_denylisted_seed(seed));
+        assert!(is_denylisted_seed_with_denylist(seed, &[seed]));
+        assert!(is_denylisted_seed_with_denylist([0u8; 16], &[]));
+        assert!(!is_denylisted_seed_with_denylist(seed, &[[1u8; 16]]));
+    }
+}
@@ -0,0 +1,313 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! derive contains helper functions for deriving new values from a seed.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Error, Result};
+use sha2::{Digest, Sha256};
+
+use crate::Seed;
+
+/// hash_fields returns the sha256 of the concatenation of 'fields'. The fields are not length
+/// prefixed, so the encoding is only unambiguous if at most one field has a variable length. Every
+/// derivation in this crate hashes a constant domain separator, fixed length values such as seeds
+/// and integers, and at most one variable length string.
+pub(crate) fn hash_fields(fields: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update(field);
+    }
+    hasher.finalize().into()
+}
+
+/// DEVICE_SEED_SPECIFIER is the domain separator used when deriving device seeds.
+const DEVICE_SEED_SPECIFIER: &[u8] = b"seed15 device seed";
+
+/// device_seed derives a child seed for a specific device from a master seed. The same master
+/// seed and device name always produce the same child seed, so every device seed can be recovered
+/// from the master seed. The child seed is a full seed and can be turned into its own phrase or
+/// keypair.
+pub fn device_seed(master: Seed, device_name: &str) -> Seed {
+    let r = hash_fields(&[DEVICE_SEED_SPECIFIER, &master, device_name.as_bytes()]);
+    let mut seed: Seed = [0u8; 16];
+    seed.copy_from_slice(&r[..16]);
+    seed
+}
+
+/// SHUFFLE_SPECIFIER is the domain separator used when deriving shuffles.
+const SHUFFLE_SPECIFIER: &[u8] = b"seed15 shuffle";
+
+/// SeedStream is a deterministic stream of random values derived from a seed, a domain separator,
+/// and a context string. Each block of output is the sha256 of the inputs and a block counter.
+struct SeedStream {
+    prefix: Sha256,
+    counter: u64,
+    block: [u8; 32],
+    used: usize,
+}
+
+impl SeedStream {
+    /// new creates a stream for the given domain separator, seed, and context.
+    fn new(specifier: &[u8], seed: Seed, context: &str) -> SeedStream {
+        // The context is length prefixed so that it can't run into the counter.
+        let mut prefix = Sha256::new();
+        prefix.update(specifier);
+        prefix.update(seed);
+        prefix.update((context.len() as u64).to_le_bytes());
+        prefix.update(context.as_bytes());
+        SeedStream {
+            prefix,
+            counter: 0,
+            block: [0u8; 32],
+            used: 32,
+        }
+    }
+
+    /// next_u64 returns the next 8 bytes of the stream as a u64.
+    fn next_u64(&mut self) -> u64 {
+        if self.used == 32 {
+            let mut hasher = self.prefix.clone();
+            hasher.update(self.counter.to_le_bytes());
+            self.block.copy_from_slice(&hasher.finalize());
+            self.counter += 1;
+            self.used = 0;
+        }
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&self.block[self.used..self.used + 8]);
+        self.used += 8;
+        u64::from_le_bytes(buf)
+    }
+
+    /// below returns a uniformly distributed value in 0..n. n must be greater than zero.
+    fn below(&mut self, n: u64) -> u64 {
+        // Reject values from the final partial range to avoid modulo bias.
+        let zone = u64::MAX - (u64::MAX % n);
+        loop {
+            let v = self.next_u64();
+            if v < zone {
+                return v % n;
+            }
+        }
+    }
+}
+
+/// shuffle_indices returns a permutation of 0..n that is derived deterministically from the seed
+/// and the context string. The same inputs always produce the same permutation, and different
+/// contexts produce independent permutations.
+pub fn shuffle_indices(seed: Seed, context: &str, n: usize) -> Vec<usize> {
+    let mut stream = SeedStream::new(SHUFFLE_SPECIFIER, seed, context);
+    let mut indices: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        let j = stream.below(i as u64 + 1) as usize;
+        indices.swap(i, j);
+    }
+    indices
+}
+
+/// PALETTE_SPECIFIER is the domain separator used when deriving color palettes.
+const PALETTE_SPECIFIER: &[u8] = b"seed15 palette";
+
+/// palette_from_seed derives 'count' RGB colors from a seed, which can be used to give each
+/// account a recognizable look. The colors are purely cosmetic: they are not a fingerprint and
+/// must not be used to confirm that a user has the right seed.
+pub fn palette_from_seed(seed: Seed, count: usize) -> Vec<[u8; 3]> {
+    let mut stream = SeedStream::new(PALETTE_SPECIFIER, seed, "");
+    (0..count)
+        .map(|_| {
+            let b = stream.next_u64().to_le_bytes();
+            [b[0], b[1], b[2]]
+        })
+        .collect()
+}
+
+/// UUID_SPECIFIER is the domain separator used when deriving UUIDs.
+const UUID_SPECIFIER: &[u8] = b"seed15 uuid";
+
+/// uuid_from_seed derives a stable UUID from a seed and a namespace, for apps that key database
+/// records to an account. The UUID is shaped like a version 4 UUID, with the version and variant
+/// bits set, but it is deterministic rather than random. Different namespaces produce unrelated
+/// UUIDs. The UUID is not secret, but it identifies the seed, so it should not be shared with
+/// parties that should not be able to link accounts.
+pub fn uuid_from_seed(seed: Seed, namespace: &str) -> [u8; 16] {
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&hash_fields(&[UUID_SPECIFIER, &seed, namespace.as_bytes()])[..16]);
+    uuid[6] = (uuid[6] & 0x0f) | 0x40;
+    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+    uuid
+}
+
+/// KEYBOARD_ENTROPY_SPECIFIER is the domain separator used when hashing keyboard entropy.
+const KEYBOARD_ENTROPY_SPECIFIER: &[u8] = b"seed15 keyboard entropy";
+
+/// KEYBOARD_ENTROPY_BITS is the estimated entropy that keyboard input must reach.
+const KEYBOARD_ENTROPY_BITS: f64 = 128.0;
+
+/// KEYBOARD_MIN_DISTINCT is the number of different characters that keyboard input must use.
+const KEYBOARD_MIN_DISTINCT: usize = 10;
+
+/// estimate_keyboard_entropy returns a conservative estimate of the bits of entropy in text typed
+/// by mashing keys. Only characters that start a new pair of characters count; characters that
+/// repeat the previous character, step to its neighbor in the alphabet, or repeat an earlier pair
+/// add nothing. Each counted character is worth half of the Shannon entropy of the character
+/// frequencies, since mashed keys cluster around the hands' resting positions.
+fn estimate_keyboard_entropy(input: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in input.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let total = input.chars().count() as f64;
+    let shannon: f64 = counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+
+    let mut pairs = HashSet::new();
+    let mut novel = 0;
+    let mut chars = input.chars();
+    let mut prev = match chars.next() {
+        Some(c) => c,
+        None => return 0.0,
+    };
+    for c in chars {
+        let step = (c as i64 - prev as i64).abs();
+        if step > 1 && pairs.insert((prev, c)) {
+            novel += 1;
+        }
+        prev = c;
+    }
+    novel as f64 * shannon / 2.0
+}
+
+/// seed_from_keyboard_entropy creates a seed by hashing text that the user typed by mashing random
+/// keys. The input is rejected unless it uses at least 10 different characters and its estimated
+/// entropy reaches 128 bits, which usually takes 60 to 100 keystrokes. The estimate is a heuristic
+/// that catches repetitive and patterned input; it can't prove that the input is random, so
+/// random_seed should be preferred whenever the device has a trustworthy random number generator.
+/// The same input always produces the same seed.
+pub fn seed_from_keyboard_entropy(input: &str) -> Result<Seed, Error> {
+    let distinct = input.chars().collect::<HashSet<char>>().len();
+    if distinct < KEYBOARD_MIN_DISTINCT {
+        bail!(
+            "input uses only {} different characters, at least {} are needed",
+            distinct,
+            KEYBOARD_MIN_DISTINCT
+        );
+    }
+    let bits = estimate_keyboard_entropy(input);
+    if bits < KEYBOARD_ENTROPY_BITS {
+        bail!(
+            "input has an estimated {:.0} bits of entropy but {:.0} are needed, keep typing",
+            bits,
+            KEYBOARD_ENTROPY_BITS
+        );
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(KEYBOARD_ENTROPY_SPECIFIER);
+    hasher.update(input.as_bytes());
+    let mut seed: Seed = [0u8; 16];
+    seed.copy_from_slice(&hasher.finalize()[..16]);
+    Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_seed;
+
+    #[test]
+    // Check that device seeds are stable and distinct per device.
+    fn check_device_seed() {
+        let master = random_seed();
+        let laptop = device_seed(master, "laptop");
+        let phone = device_seed(master, "phone");
+        assert_ne!(laptop, phone);
+        assert_ne!(laptop, master);
+        assert_eq!(laptop, device_seed(master, "laptop"));
+        assert_eq!(phone, device_seed(master, "phone"));
+        assert_ne!(laptop, device_seed(random_seed(), "laptop"));
+
+        // Device seeds must never change for a given master seed and name.
+        let expected = [
+            69, 11, 114, 108, 187, 124, 1, 196, 143, 74, 155, 204, 198, 207, 25, 155,
+        ];
+        assert_eq!(device_seed([0u8; 16], "laptop"), expected);
+    }
+
+    #[test]
+    // Check that shuffles are deterministic, valid permutations, and depend on every input.
+    fn check_shuffle_indices() {
+        let seed = random_seed();
+        let shuffle = shuffle_indices(seed, "ui", 100);
+        assert_eq!(shuffle, shuffle_indices(seed, "ui", 100));
+        let mut sorted = shuffle.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..100).collect::<Vec<usize>>());
+
+        assert_ne!(shuffle, shuffle_indices(seed, "shards", 100));
+        assert_ne!(shuffle, shuffle_indices(random_seed(), "ui", 100));
+        assert!(shuffle_indices(seed, "ui", 0).is_empty());
+        assert_eq!(shuffle_indices(seed, "ui", 1), vec![0]);
+    }
+
+    #[test]
+    // Check that palettes are deterministic and depend on the seed.
+    fn check_palette_from_seed() {
+        let seed = random_seed();
+        let palette = palette_from_seed(seed, 5);
+        assert_eq!(palette.len(), 5);
+        assert_eq!(palette, palette_from_seed(seed, 5));
+        assert_eq!(palette[..3], palette_from_seed(seed, 3)[..]);
+        assert_ne!(palette, palette_from_seed(random_seed(), 5));
+        assert!(palette_from_seed(seed, 0).is_empty());
+    }
+
+    #[test]
+    // Check that UUIDs are deterministic, depend on both inputs, and have the version 4 shape.
+    fn check_uuid_from_seed() {
+        let seed = random_seed();
+        let uuid = uuid_from_seed(seed, "accounts");
+        assert_eq!(uuid, uuid_from_seed(seed, "accounts"));
+        assert_ne!(uuid, uuid_from_seed(seed, "account"));
+        assert_ne!(uuid, uuid_from_seed(random_seed(), "accounts"));
+        assert_ne!(uuid_from_seed(seed, ""), uuid_from_seed(seed, "a"));
+        for i in 0..100 {
+            let uuid = uuid_from_seed(random_seed(), &i.to_string());
+            assert_eq!(uuid[6] >> 4, 4);
+            assert_eq!(uuid[8] >> 6, 0b10);
+        }
+    }
+
+    #[test]
+    // Random keystrokes are accepted and patterned input is rejected.
+    fn check_seed_from_keyboard_entropy() {
+        let keys = b"qwertyuiopasdfghjklzxcvbnm1234567890";
+        let mut input = String::new();
+        while input.len() < 100 {
+            for b in random_seed() {
+                input.push(char::from(keys[b as usize % keys.len()]));
+            }
+        }
+        let seed = seed_from_keyboard_entropy(&input).unwrap();
+        assert_eq!(seed, seed_from_keyboard_entropy(&input).unwrap());
+        assert_ne!(seed, seed_from_keyboard_entropy(&input[1..]).unwrap());
+
+        // Short random input doesn't have enough entropy yet.
+        let err = seed_from_keyboard_entropy(&input[..20]).unwrap_err();
+        assert!(err.to_string().contains("keep typing"));
+
+        seed_from_keyboard_entropy("").unwrap_err();
+        seed_from_keyboard_entropy("aaaaaaaa").unwrap_err();
+        seed_from_keyboard_entropy(&"asdf".repeat(50)).unwrap_err();
+        seed_from_keyboard_entropy(&"qwertyuiop".repeat(20)).unwrap_err();
+        seed_from_keyboard_entropy(&"abcdefghijklmnopqrstuvwxyz".repeat(10)).unwrap_err();
+        seed_from_keyboard_entropy(&"aabbccddeeffgghhiijjkk".repeat(10)).unwrap_err();
+    }
+}
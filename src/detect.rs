@@ -0,0 +1,260 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! detect contains helpers for spotting seed phrases inside of arbitrary text, for example to warn
+//! a user who is about to paste their seed phrase into a chat window.
+
+use std::ops::Range;
+
+use dictionary_1024::{DICTIONARY, DICTIONARY_UNIQUE_PREFIX};
+
+use crate::phrase::{
+    checksum_word_indices, entropy_indices_to_seed, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS,
+};
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// PhraseLikelihood describes how likely it is that a piece of text contains a seed phrase. The
+/// range is the byte range of the match within the scanned text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PhraseLikelihood {
+    /// None indicates that the text does not contain 15 consecutive dictionary words.
+    None,
+    /// Possible indicates that the text contains 15 consecutive dictionary words, but they do not
+    /// form a valid seed phrase.
+    Possible(Range<usize>),
+    /// Definite indicates that the text contains a seed phrase with a valid checksum.
+    Definite(Range<usize>),
+}
+
+/// looks_like_seed_phrase scans text for runs of 15 consecutive dictionary words. Words are
+/// matched case-insensitively by their first 3 letters, the same way seed_phrase_to_seed reads
+/// them, so phrases abbreviated to 3 letter prefixes are detected. Words may be separated by any
+/// non-alphabetic characters, so numbered lists and comma separated phrases are detected as well.
+///
+/// If any run of 15 words has a valid checksum, the first such run is returned as Definite.
+/// Otherwise the first run of 15 words is returned as Possible. find_seed_phrases returns every
+/// match instead of only the first.
+pub fn looks_like_seed_phrase(text: &str) -> PhraseLikelihood {
+    let mut possible = None;
+    let mut definite = None;
+    scan(text, |m| match m {
+        PhraseLikelihood::Definite(_) => {
+            definite = Some(m);
+            false
+        }
+        _ => {
+            if possible.is_none() {
+                possible = Some(m);
+            }
+            true
+        }
+    });
+    match (definite, possible) {
+        (Some(m), _) | (None, Some(m)) => m,
+        (None, None) => PhraseLikelihood::None,
+    }
+}
+
+/// find_seed_phrases returns every match in the text, in order. Every run of 15 words with a
+/// valid checksum is returned as Definite, and so a paste containing several phrases reports all
+/// of them. A stretch of consecutive dictionary words that contains no valid phrase is returned
+/// once, as Possible covering its first 15 words. Matching works as in looks_like_seed_phrase.
+pub fn find_seed_phrases(text: &str) -> Vec<PhraseLikelihood> {
+    let mut matches = Vec::new();
+    scan(text, |m| {
+        matches.push(m);
+        true
+    });
+    matches
+}
+
+/// scan implements looks_like_seed_phrase and find_seed_phrases, passing each match to 'found' in
+/// order until it returns false.
+fn scan(text: &str, mut found: impl FnMut(PhraseLikelihood) -> bool) {
+    // Keep the most recent 15 dictionary words in a ring buffer so that no copies of the text are
+    // made while scanning. 'possible' is the first window of the current run of words, and is
+    // reported when the run ends without containing a valid phrase.
+    let mut window = [(0usize, 0usize); PHRASE_WORDS];
+    let mut run = 0;
+    let mut possible = None;
+    let mut run_definite = false;
+    for token in tokens(text).map(Some).chain(std::iter::once(None)) {
+        let index = token.as_ref().and_then(|t| prefix_index(&text[t.clone()]));
+        let (token, index) = match (token, index) {
+            (Some(token), Some(index)) => (token, index),
+            _ => {
+                if let Some(range) = possible.take() {
+                    if !run_definite && !found(PhraseLikelihood::Possible(range)) {
+                        return;
+                    }
+                }
+                run = 0;
+                run_definite = false;
+                continue;
+            }
+        };
+        window[run % PHRASE_WORDS] = (token.start, index);
+        run += 1;
+        if run < PHRASE_WORDS {
+            continue;
+        }
+
+        // Read the window in order, oldest word first.
+        let mut indices = [0usize; PHRASE_WORDS];
+        for (i, index) in indices.iter_mut().enumerate() {
+            *index = window[(run + i) % PHRASE_WORDS].1;
+        }
+        let start = window[run % PHRASE_WORDS].0;
+        let end = token.end;
+        if valid_indices(&indices) {
+            run_definite = true;
+            if !found(PhraseLikelihood::Definite(start..end)) {
+                return;
+            }
+        }
+        if possible.is_none() {
+            possible = Some(start..end);
+        }
+    }
+}
+
+/// tokens returns the byte ranges of each run of ascii letters in the text.
+fn tokens(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while pos < bytes.len() && !bytes[pos].is_ascii_alphabetic() {
+            pos += 1;
+        }
+        if pos == bytes.len() {
+            return None;
+        }
+        let start = pos;
+        while pos < bytes.len() && bytes[pos].is_ascii_alphabetic() {
+            pos += 1;
+        }
+        Some(start..pos)
+    })
+}
+
+/// prefix_index returns the dictionary index of the word whose first 3 letters match the word,
+/// ignoring case, like dictionary::index_of.
+fn prefix_index(word: &str) -> Option<usize> {
+    let prefix = word.as_bytes().get(..DICTIONARY_UNIQUE_PREFIX)?;
+    let mut lower = [0u8; DICTIONARY_UNIQUE_PREFIX];
+    for (l, c) in lower.iter_mut().zip(prefix) {
+        *l = c.to_ascii_lowercase();
+    }
+    DICTIONARY
+        .iter()
+        .position(|w| w.as_bytes()[..DICTIONARY_UNIQUE_PREFIX] == lower)
+}
+
+/// valid_indices returns true if the dictionary indices form a seed phrase with a valid checksum.
+pub(crate) fn valid_indices(indices: &[usize; PHRASE_WORDS]) -> bool {
+    let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+    entropy.copy_from_slice(&indices[..SEED_ENTROPY_WORDS]);
+    let seed = match entropy_indices_to_seed(&entropy) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let (c1, c2) = checksum_word_indices(seed);
+    c1 == indices[SEED_ENTROPY_WORDS] && c2 == indices[SEED_ENTROPY_WORDS + 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_to_seed_phrase;
+    use crate::random_seed;
+
+    #[test]
+    // Check detection of exact phrases, embedded phrases, invalid phrases, and ordinary text.
+    fn check_looks_like_seed_phrase() {
+        // An exact phrase should be definite and cover the whole string.
+        let phrase = seed_to_seed_phrase(random_seed());
+        assert_eq!(
+            looks_like_seed_phrase(&phrase),
+            PhraseLikelihood::Definite(0..phrase.len())
+        );
+
+        // A phrase inside a sentence, with different capitalization and separators.
+        let numbered: Vec<String> = phrase
+            .split(' ')
+            .enumerate()
+            .map(|(i, w)| format!("{}. {}", i + 1, w.to_uppercase()))
+            .collect();
+        let numbered = numbered.join(", ");
+        let text = format!("hey, here is my seed: {} - keep it safe", numbered);
+        let start = text.find("1. ").unwrap() + 3;
+        let end = start + numbered.len() - 3;
+        assert_eq!(
+            looks_like_seed_phrase(&text),
+            PhraseLikelihood::Definite(start..end)
+        );
+
+        // A second phrase following an invalid run should still be found.
+        let bad_run = DICTIONARY[..15].join(" ");
+        let text = format!("{} then {}", bad_run, phrase);
+        let start = bad_run.len() + 6;
+        assert_eq!(
+            looks_like_seed_phrase(&text),
+            PhraseLikelihood::Definite(start..text.len())
+        );
+
+        // 15 dictionary words with a bad checksum are only possible.
+        assert_eq!(
+            looks_like_seed_phrase(&bad_run),
+            PhraseLikelihood::Possible(0..bad_run.len())
+        );
+
+        // Ordinary english text, and text with fewer than 15 dictionary words.
+        let text = "The quick brown fox jumps over the lazy dog, then naps under a tree.";
+        assert_eq!(looks_like_seed_phrase(text), PhraseLikelihood::None);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let text = words[..14].join(" ");
+        assert_eq!(looks_like_seed_phrase(&text), PhraseLikelihood::None);
+
+        // A phrase abbreviated to 3 letter prefixes is detected, since it parses.
+        let abbreviated: Vec<String> = words.iter().map(|w| w[..3].to_uppercase()).collect();
+        let abbreviated = abbreviated.join(" ");
+        assert_eq!(
+            looks_like_seed_phrase(&abbreviated),
+            PhraseLikelihood::Definite(0..abbreviated.len())
+        );
+    }
+
+    #[test]
+    // Check that every phrase and every invalid run in the text is reported, in order.
+    fn check_find_seed_phrases() {
+        let first = seed_to_seed_phrase(random_seed());
+        let second = seed_to_seed_phrase(random_seed());
+        let bad_run = DICTIONARY[..15].join(" ");
+        let text = format!("{} qqq {} qqq {}", bad_run, first, second);
+        let first_start = bad_run.len() + 5;
+        let second_start = first_start + first.len() + 5;
+        assert_eq!(
+            find_seed_phrases(&text),
+            vec![
+                PhraseLikelihood::Possible(0..bad_run.len()),
+                PhraseLikelihood::Definite(first_start..first_start + first.len()),
+                PhraseLikelihood::Definite(second_start..text.len()),
+            ]
+        );
+        assert_eq!(
+            looks_like_seed_phrase(&text),
+            PhraseLikelihood::Definite(first_start..first_start + first.len())
+        );
+
+        // A run with a valid phrase is not also reported as possible.
+        let text = format!("{} {}", first, DICTIONARY[..5].join(" "));
+        assert_eq!(
+            find_seed_phrases(&text),
+            vec![PhraseLikelihood::Definite(0..first.len())]
+        );
+        assert!(find_seed_phrases("nothing to see here").is_empty());
+    }
+}
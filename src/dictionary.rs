@@ -0,0 +1,164 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! dictionary contains helpers for working with the dictionary-1024 word list that seed phrases
+//! are built from.
+
+use anyhow::{bail, Error, Result};
+use dictionary_1024::{DICTIONARY, DICTIONARY_UNIQUE_PREFIX};
+
+/// word_at returns the dictionary word at 'index', or None if the index is out of range. Unlike
+/// dictionary_1024::word_at_index, it never panics and does not allocate.
+pub fn word_at(index: usize) -> Option<&'static str> {
+    DICTIONARY.get(index).copied()
+}
+
+/// index_of returns the dictionary index of 'word', using only the first DICTIONARY_UNIQUE_PREFIX
+/// characters like dictionary_1024::index_of_word. None is returned if the word is too short or
+/// its prefix is not in the dictionary. Unlike index_of_word, it never panics, including on words
+/// that contain multi-byte characters.
+pub fn index_of(word: &str) -> Option<u16> {
+    let prefix = word.as_bytes().get(..DICTIONARY_UNIQUE_PREFIX)?;
+    DICTIONARY
+        .iter()
+        .position(|w| &w.as_bytes()[..DICTIONARY_UNIQUE_PREFIX] == prefix)
+        .map(|i| i as u16)
+}
+
+/// same_word returns true if 'a' and 'b' are the same dictionary word by prefix. It is the
+/// non-panicking replacement for dictionary_1024::words_match. Like the phrase parser, it only
+/// accepts ascii words, so words that are too short, not in the dictionary, or that contain
+/// non-ascii characters anywhere never match.
+pub(crate) fn same_word(a: &str, b: &str) -> bool {
+    if !a.is_ascii() || !b.is_ascii() {
+        return false;
+    }
+    match (index_of(a), index_of(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// dictionary_entries yields every (index, word) pair of the dictionary in index order, for
+/// building word pickers and tests.
+pub fn dictionary_entries() -> impl Iterator<Item = (usize, String)> {
+    DICTIONARY.iter().map(|w| w.to_string()).enumerate()
+}
+
+/// lookup_index is index_of with an error that explains why the lookup failed.
+pub(crate) fn lookup_index(word: &str) -> Result<usize, Error> {
+    if word.len() < DICTIONARY_UNIQUE_PREFIX {
+        bail!(
+            "each word must have at least {} characters",
+            DICTIONARY_UNIQUE_PREFIX
+        );
+    }
+    match index_of(word) {
+        Some(index) => Ok(index as usize),
+        None => bail!("word '{}' was not found in dictionary", word),
+    }
+}
+
+/// min_unique_prefix returns the length of the shortest prefix of 'word' that is not shared with
+/// any other word in the dictionary. None is returned if 'word' is not in the dictionary. Note
+/// that seed phrase parsing always requires at least DICTIONARY_UNIQUE_PREFIX characters, even
+/// for words that are identified by a shorter prefix.
+pub fn min_unique_prefix(word: &str) -> Option<usize> {
+    if !DICTIONARY.contains(&word) {
+        return None;
+    }
+    (1..=word.len()).find(|&len| {
+        let prefix = &word[..len];
+        DICTIONARY.iter().filter(|w| w.starts_with(prefix)).count() == 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // Check words with known prefix behavior.
+    fn check_min_unique_prefix() {
+        // 'abbey' shares 'ab' with 'able'.
+        assert_eq!(min_unique_prefix("abbey"), Some(3));
+        // No other word starts with 'ae'.
+        assert_eq!(min_unique_prefix("aerial"), Some(2));
+        assert_eq!(min_unique_prefix("ozone"), Some(2));
+        assert_eq!(min_unique_prefix("abb"), None);
+        assert_eq!(min_unique_prefix("notaword"), None);
+
+        // Every word in the dictionary has a unique prefix of at most 3 characters.
+        for word in DICTIONARY {
+            assert!(min_unique_prefix(word).unwrap() <= 3);
+        }
+    }
+
+    #[test]
+    // word_at and index_of must reject bad input without panicking.
+    fn check_word_at_index_of() {
+        assert_eq!(word_at(0), Some(DICTIONARY[0]));
+        assert_eq!(word_at(1023), Some(DICTIONARY[1023]));
+        assert_eq!(word_at(1024), None);
+        assert_eq!(word_at(usize::MAX), None);
+        for (i, word) in DICTIONARY.iter().enumerate() {
+            assert_eq!(index_of(word), Some(i as u16));
+            assert_eq!(index_of(&word[..3]), Some(i as u16));
+        }
+        assert_eq!(index_of(""), None);
+        assert_eq!(index_of("ab"), None);
+        assert_eq!(index_of("zzz"), None);
+        assert_eq!(index_of("ab\u{e9}"), None);
+        assert_eq!(index_of("\u{e9}\u{e9}"), None);
+        assert_eq!(index_of("abb\u{e9}"), index_of("abbey"));
+        lookup_index("ab").unwrap_err();
+        lookup_index("zzz").unwrap_err();
+        assert_eq!(lookup_index("abbey").unwrap(), 0);
+
+        // Arbitrary strings never panic, and agree with index_of_word whenever it can be called.
+        for _ in 0..10_000 {
+            let bytes = crate::random_seed();
+            let len = (bytes[0] % 8) as usize;
+            let word: String = bytes[1..1 + len]
+                .iter()
+                .map(|b| match b % 4 {
+                    0 => char::from(b'a' + b % 26),
+                    1 => char::from_u32(0x80 + *b as u32 * 3).unwrap(),
+                    _ => char::from(b"abcelmos"[(b % 8) as usize]),
+                })
+                .collect();
+            let index = index_of(&word);
+            if word.is_char_boundary(DICTIONARY_UNIQUE_PREFIX.min(word.len())) {
+                let expected = dictionary_1024::index_of_word(&word).ok();
+                assert_eq!(index.map(|i| i as usize), expected, "{:?}", word);
+            }
+        }
+    }
+
+    #[test]
+    // same_word must match words by prefix and reject everything else without panicking.
+    fn check_same_word() {
+        assert!(same_word("abbey", "abbey"));
+        assert!(same_word("abbey", "abb"));
+        assert!(same_word("abbey", "abbot"));
+        assert!(!same_word("abbey", "able"));
+        assert!(!same_word("abbey", "ab"));
+        assert!(!same_word("zzz", "zzz"));
+        assert!(!same_word("abbey", "abb\u{e9}"));
+        assert!(!same_word("\u{e9}\u{e9}", "\u{e9}\u{e9}"));
+    }
+
+    #[test]
+    // dictionary_entries must cover the whole dictionary in order.
+    fn check_dictionary_entries() {
+        assert_eq!(dictionary_entries().count(), 1024);
+        let (index, word) = dictionary_entries().nth(500).unwrap();
+        assert_eq!(index, 500);
+        assert_eq!(word, dictionary_1024::word_at_index(500));
+        for (index, word) in dictionary_entries() {
+            assert_eq!(index_of(&word), Some(index as u16));
+        }
+    }
+}
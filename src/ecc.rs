@@ -0,0 +1,320 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! ecc contains a 17 word variant of the seed phrase that can correct a wrong word without a
+//! brute force search. The first 15 words are the standard phrase, followed by two parity words
+//! from a Reed-Solomon code over GF(1024), with one symbol per word.
+//!
+//! The code covers the 13 entropy words and the two parity words, which gives 15 symbols with a
+//! minimum distance of 3: any single wrong symbol can be located and corrected. The checksum
+//! words are not part of the code, but a wrong checksum word is easy to correct once the entropy
+//! is known to be right. The code alone cannot tell two wrong words from one, so after a
+//! correction the checksum words are checked as well. Two wrong words are therefore detected
+//! unless the miscorrected seed happens to pass the checksum, which has the usual one in a
+//! million chance.
+//!
+//! Field elements are polynomials over GF(2) reduced by x^10 + x^3 + 1, and the generator
+//! polynomial of the code is (x + a)(x + a^2), where a is the element x.
+
+use anyhow::{bail, Error, Result};
+use dictionary_1024::DICTIONARY;
+
+use crate::dictionary::index_of;
+use crate::phrase::{
+    checksum_word_indices, entropy_indices_to_seed, seed_to_seed_phrase, SEED_CHECKSUM_WORDS,
+    SEED_ENTROPY_WORDS,
+};
+use crate::Seed;
+
+/// ECC_PARITY_WORDS is the number of parity words appended to the standard phrase.
+pub const ECC_PARITY_WORDS: usize = 2;
+
+/// PHRASE_WORDS is the number of words in a standard phrase.
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// ECC_PHRASE_WORDS is the number of words in an error-correcting phrase.
+const ECC_PHRASE_WORDS: usize = PHRASE_WORDS + ECC_PARITY_WORDS;
+
+/// CODE_SYMBOLS is the length of the Reed-Solomon codeword: the entropy words and the parity.
+const CODE_SYMBOLS: usize = SEED_ENTROPY_WORDS + ECC_PARITY_WORDS;
+
+/// GF_POLY is the reduction polynomial x^10 + x^3 + 1, which is primitive.
+const GF_POLY: u16 = 0x409;
+
+/// GF_ALPHA is the primitive element x.
+const GF_ALPHA: u16 = 2;
+
+/// CorrectionReport describes what seed_phrase_to_seed_ecc had to change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorrectionReport {
+    /// Clean indicates that every word was correct.
+    Clean,
+    /// Corrected indicates that one word was wrong and has been corrected.
+    Corrected {
+        /// position is the index of the corrected word within the 17 words.
+        position: usize,
+    },
+}
+
+/// gf_mul multiplies two elements of GF(1024).
+fn gf_mul(a: u16, b: u16) -> u16 {
+    let (mut a, mut b, mut product) = (a, b, 0u16);
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        b >>= 1;
+        a <<= 1;
+        if a & 0x400 != 0 {
+            a ^= GF_POLY;
+        }
+    }
+    product
+}
+
+/// gf_pow raises an element of GF(1024) to a power.
+fn gf_pow(a: u16, mut exp: u32) -> u16 {
+    let (mut base, mut result) = (a, 1u16);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// gf_inv returns the multiplicative inverse of a nonzero element of GF(1024).
+fn gf_inv(a: u16) -> u16 {
+    gf_pow(a, 1022)
+}
+
+/// code_position returns the position within the 17 words of codeword symbol 'k', which is the
+/// coefficient of x^k. The entropy words are the high coefficients, first word highest, and the
+/// parity words are the two lowest.
+fn code_position(k: usize) -> usize {
+    if k >= ECC_PARITY_WORDS {
+        CODE_SYMBOLS - 1 - k
+    } else {
+        ECC_PHRASE_WORDS - 1 - k
+    }
+}
+
+/// parity_symbols returns the coefficients of x^1 and x^0 of the codeword for the entropy words.
+fn parity_symbols(entropy: &[usize]) -> [u16; ECC_PARITY_WORDS] {
+    // The generator is x^2 + g1 x + g0. Divide m(x) x^2 by it with a shift register; the
+    // remainder is the parity.
+    let g1 = GF_ALPHA ^ gf_mul(GF_ALPHA, GF_ALPHA);
+    let g0 = gf_pow(GF_ALPHA, 3);
+    let (mut r1, mut r0) = (0u16, 0u16);
+    for &symbol in entropy {
+        let feedback = symbol as u16 ^ r1;
+        r1 = r0 ^ gf_mul(feedback, g1);
+        r0 = gf_mul(feedback, g0);
+    }
+    [r1, r0]
+}
+
+/// syndromes evaluates the codeword at a and a^2. Both are zero for a valid codeword.
+fn syndromes(code: &[u16; CODE_SYMBOLS]) -> (u16, u16) {
+    let (mut s1, mut s2) = (0u16, 0u16);
+    for (k, &c) in code.iter().enumerate() {
+        s1 ^= gf_mul(c, gf_pow(GF_ALPHA, k as u32));
+        s2 ^= gf_mul(c, gf_pow(GF_ALPHA, 2 * k as u32));
+    }
+    (s1, s2)
+}
+
+/// seed_to_seed_phrase_ecc converts a seed to a 17 word phrase: the standard 15 word phrase
+/// followed by two parity words. Dropping the parity words gives a standard phrase.
+pub fn seed_to_seed_phrase_ecc(seed: Seed) -> String {
+    let phrase = seed_to_seed_phrase(seed);
+    let entropy: Vec<usize> = phrase
+        .split(' ')
+        .take(SEED_ENTROPY_WORDS)
+        .map(|w| index_of(w).expect("phrase words are in the dictionary") as usize)
+        .collect();
+    let [p1, p0] = parity_symbols(&entropy);
+    format!(
+        "{} {} {}",
+        phrase, DICTIONARY[p1 as usize], DICTIONARY[p0 as usize]
+    )
+}
+
+/// seed_phrase_to_seed_ecc converts a phrase produced by seed_to_seed_phrase_ecc to a seed,
+/// correcting up to one wrong word. The wrong word may be any dictionary word or may not be in
+/// the dictionary at all. Like seed_phrase_to_seed, only the dictionary prefix of each word is
+/// considered. An error is returned if the phrase does not have 17 words or has more wrong words
+/// than can be corrected.
+pub fn seed_phrase_to_seed_ecc(phrase: &str) -> Result<(Seed, CorrectionReport), Error> {
+    let words: Vec<&str> = phrase.split(' ').collect();
+    if words.len() != ECC_PHRASE_WORDS {
+        bail!(
+            "expecting {} words but got {} words",
+            ECC_PHRASE_WORDS,
+            words.len()
+        );
+    }
+
+    // Words that aren't in the dictionary are read as index zero, the decoder then has to
+    // correct them.
+    let indices: Vec<Option<usize>> = words
+        .iter()
+        .map(|w| index_of(w).map(|i| i as usize))
+        .collect();
+    let unknown: Vec<usize> = (0..ECC_PHRASE_WORDS)
+        .filter(|&i| indices[i].is_none())
+        .collect();
+    if unknown.len() > 1 {
+        bail!("more than one word is not in the dictionary, the phrase can't be corrected");
+    }
+    let mut code = [0u16; CODE_SYMBOLS];
+    for (k, c) in code.iter_mut().enumerate() {
+        *c = indices[code_position(k)].unwrap_or(0) as u16;
+    }
+
+    // A single error with value e at x^k gives syndromes e a^k and e a^2k, so the location is
+    // their ratio.
+    let mut corrected = None;
+    match syndromes(&code) {
+        (0, 0) => {}
+        (s1, s2) if s1 != 0 && s2 != 0 => {
+            let locator = gf_mul(s2, gf_inv(s1));
+            let k = match (0..CODE_SYMBOLS).find(|&k| gf_pow(GF_ALPHA, k as u32) == locator) {
+                Some(k) => k,
+                None => bail!("phrase has more wrong words than can be corrected"),
+            };
+            code[k] ^= gf_mul(s1, gf_inv(locator));
+            corrected = Some(code_position(k));
+        }
+        _ => bail!("phrase has more wrong words than can be corrected"),
+    }
+
+    // An unknown word in the codeword that passes without correction stood for index zero.
+    let in_code = |p: usize| !(SEED_ENTROPY_WORDS..PHRASE_WORDS).contains(&p);
+    if corrected.is_none() {
+        corrected = unknown.first().copied().filter(|&p| in_code(p));
+    }
+
+    let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+    for (i, e) in entropy.iter_mut().enumerate() {
+        *e = code[CODE_SYMBOLS - 1 - i] as usize;
+    }
+    let seed = match entropy_indices_to_seed(&entropy) {
+        Ok(seed) => seed,
+        Err(_) => bail!("phrase has more wrong words than can be corrected"),
+    };
+
+    // The checksum words either confirm the correction or hold the single wrong word.
+    let (c1, c2) = checksum_word_indices(seed);
+    for (position, expected) in [(SEED_ENTROPY_WORDS, c1), (SEED_ENTROPY_WORDS + 1, c2)] {
+        if indices[position] == Some(expected) {
+            continue;
+        }
+        if corrected.is_some() {
+            bail!("phrase has more wrong words than can be corrected");
+        }
+        corrected = Some(position);
+    }
+    if unknown.iter().any(|&u| Some(u) != corrected) {
+        bail!("phrase has more wrong words than can be corrected");
+    }
+
+    match corrected {
+        Some(position) => Ok((seed, CorrectionReport::Corrected { position })),
+        None => Ok((seed, CorrectionReport::Clean)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_phrase_to_seed;
+    use crate::random_seed;
+
+    #[test]
+    // The reduction polynomial must be primitive for the code to work.
+    fn check_gf() {
+        let mut seen = vec![false; 1024];
+        let mut x = 1u16;
+        for _ in 0..1023 {
+            assert!(!seen[x as usize]);
+            seen[x as usize] = true;
+            x = gf_mul(x, GF_ALPHA);
+        }
+        assert_eq!(x, 1);
+        for a in 1..1024u16 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    // Correct one wrong word at every position and detect two wrong words.
+    fn check_seed_phrase_ecc() {
+        for _ in 0..10 {
+            let seed = random_seed();
+            let phrase = seed_to_seed_phrase_ecc(seed);
+            let words: Vec<&str> = phrase.split(' ').collect();
+            assert_eq!(words.len(), ECC_PHRASE_WORDS);
+            assert_eq!(
+                seed_phrase_to_seed(&words[..PHRASE_WORDS].join(" ")).unwrap(),
+                seed
+            );
+            assert_eq!(
+                seed_phrase_to_seed_ecc(&phrase).unwrap(),
+                (seed, CorrectionReport::Clean)
+            );
+
+            let expected = |position| Ok((seed, CorrectionReport::Corrected { position }));
+            for position in 0..ECC_PHRASE_WORDS {
+                let original = index_of(words[position]).unwrap() as usize;
+                for replacement in [
+                    DICTIONARY[(original + 1) % 1024],
+                    DICTIONARY[(original + 517) % 1024],
+                    "zzz",
+                ] {
+                    let mut corrupted = words.clone();
+                    corrupted[position] = replacement;
+                    let result = seed_phrase_to_seed_ecc(&corrupted.join(" "));
+                    assert_eq!(result.map_err(|e| e.to_string()), expected(position));
+                }
+            }
+
+            // Two wrong words are detected.
+            for (a, b) in [
+                (0, 1),
+                (3, 12),
+                (12, 13),
+                (13, 14),
+                (14, 16),
+                (15, 16),
+                (2, 15),
+            ] {
+                let mut corrupted = words.clone();
+                corrupted[a] = if words[a] == "abbey" { "able" } else { "abbey" };
+                corrupted[b] = "zzz";
+                seed_phrase_to_seed_ecc(&corrupted.join(" ")).unwrap_err();
+                corrupted[b] = if words[b] == "abbey" { "able" } else { "abbey" };
+                seed_phrase_to_seed_ecc(&corrupted.join(" ")).unwrap_err();
+            }
+        }
+
+        // An unknown word standing in for the first dictionary word is still corrected.
+        let seed = [0u8; 16];
+        let phrase = seed_to_seed_phrase_ecc(seed);
+        assert!(phrase.starts_with("abbey "));
+        let corrupted = phrase.replacen("abbey", "zzz", 1);
+        assert_eq!(
+            seed_phrase_to_seed_ecc(&corrupted).unwrap(),
+            (seed, CorrectionReport::Corrected { position: 0 })
+        );
+
+        seed_phrase_to_seed_ecc("").unwrap_err();
+        let phrase = seed_to_seed_phrase(random_seed());
+        seed_phrase_to_seed_ecc(&phrase).unwrap_err();
+        seed_phrase_to_seed_ecc(&format!("{} zzz zzz", phrase)).unwrap_err();
+    }
+}
@@ -0,0 +1,329 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! entry contains a state machine for entering a seed phrase one letter at a time, intended for
+//! devices with very limited input such as hardware wallets. The state machine does not allocate;
+//! candidate words are returned as slices of the dictionary, which is sorted, so the words that
+//! share a prefix are always contiguous.
+
+use anyhow::{bail, Error, Result};
+use dictionary_1024::DICTIONARY;
+
+use crate::phrase::{
+    checksum_word_indices, entropy_indices_to_seed, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS,
+};
+use crate::Seed;
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// MAX_PREFIX is the length of the longest word in the dictionary.
+const MAX_PREFIX: usize = 6;
+
+/// LetterEntryState describes the state of a LetterEntry after an input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LetterEntryState {
+    /// Candidates indicates that the current prefix matches this many words.
+    Candidates(usize),
+    /// Unique indicates that the current prefix matches exactly one word, which can be accepted.
+    Unique(&'static str),
+    /// Rejected indicates that the letter was ignored because no word would match.
+    Rejected,
+    /// Complete indicates that all 15 words have been entered.
+    Complete,
+}
+
+/// LetterEntry tracks the progress of entering a seed phrase letter by letter.
+#[derive(Clone, Debug, Default)]
+pub struct LetterEntry {
+    prefix: [u8; MAX_PREFIX],
+    prefix_len: usize,
+    position: usize,
+    indices: [usize; PHRASE_WORDS],
+}
+
+impl LetterEntry {
+    /// new returns a LetterEntry positioned at the first letter of the first word.
+    pub fn new() -> LetterEntry {
+        LetterEntry::default()
+    }
+
+    /// position returns the index of the word currently being entered.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// push_letter adds a letter to the current word. Letters are case-insensitive. If no
+    /// candidate word starts with the new prefix, the letter is ignored and Rejected is returned.
+    pub fn push_letter(&mut self, c: char) -> LetterEntryState {
+        if self.position == PHRASE_WORDS
+            || !c.is_ascii_alphabetic()
+            || self.prefix_len == MAX_PREFIX
+        {
+            return LetterEntryState::Rejected;
+        }
+        self.prefix[self.prefix_len] = c.to_ascii_lowercase() as u8;
+        self.prefix_len += 1;
+        if self.remaining_candidates().is_empty() {
+            self.prefix_len -= 1;
+            return LetterEntryState::Rejected;
+        }
+        self.state()
+    }
+
+    /// remaining_candidates returns every word that can still be entered at the current position.
+    /// The 13th word is restricted to the first 256 words of the dictionary.
+    pub fn remaining_candidates(&self) -> &'static [&'static str] {
+        if self.position == PHRASE_WORDS {
+            return &[];
+        }
+        let words: &'static [&'static str] = if self.position == SEED_ENTROPY_WORDS - 1 {
+            &DICTIONARY[..256]
+        } else {
+            &DICTIONARY
+        };
+        words_with_prefix(words, &self.prefix[..self.prefix_len])
+    }
+
+    /// auto_complete returns the only remaining candidate, if exactly one remains.
+    pub fn auto_complete(&self) -> Option<&'static str> {
+        match self.remaining_candidates() {
+            [word] => Some(word),
+            _ => None,
+        }
+    }
+
+    /// accept_word accepts the auto-completed word for the current position and advances to the
+    /// next position. An error is returned if more than one candidate remains.
+    pub fn accept_word(&mut self) -> Result<LetterEntryState, Error> {
+        let word = match self.auto_complete() {
+            Some(w) => w,
+            None => bail!(
+                "cannot accept word, {} candidates remain",
+                self.remaining_candidates().len()
+            ),
+        };
+        // The candidates are a subslice of the dictionary, so the index can be recovered from
+        // the word's position in the sorted list.
+        self.indices[self.position] = DICTIONARY.partition_point(|w| *w < word);
+        self.position += 1;
+        self.prefix_len = 0;
+        Ok(self.state())
+    }
+
+    /// backspace removes the last letter of the current word. If the current word is empty, the
+    /// previous word is un-accepted and must be entered again from its first letter.
+    pub fn backspace(&mut self) -> LetterEntryState {
+        if self.prefix_len > 0 {
+            self.prefix_len -= 1;
+        } else if self.position > 0 {
+            self.position -= 1;
+        }
+        self.state()
+    }
+
+    /// seed returns the seed for a completely entered phrase, verifying the checksum words.
+    pub fn seed(&self) -> Result<Seed, Error> {
+        if self.position != PHRASE_WORDS {
+            bail!("only {} of {} words entered", self.position, PHRASE_WORDS);
+        }
+        let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+        entropy.copy_from_slice(&self.indices[..SEED_ENTROPY_WORDS]);
+        let seed = entropy_indices_to_seed(&entropy)?;
+        let (c1, c2) = checksum_word_indices(seed);
+        if c1 != self.indices[SEED_ENTROPY_WORDS] || c2 != self.indices[SEED_ENTROPY_WORDS + 1] {
+            bail!("checksum words are incorrect");
+        }
+        Ok(seed)
+    }
+
+    /// state returns the state for the current prefix.
+    fn state(&self) -> LetterEntryState {
+        if self.position == PHRASE_WORDS {
+            return LetterEntryState::Complete;
+        }
+        match self.remaining_candidates() {
+            [word] => LetterEntryState::Unique(word),
+            words => LetterEntryState::Candidates(words.len()),
+        }
+    }
+}
+
+/// words_with_prefix returns the words of a sorted word list that start with 'prefix'.
+fn words_with_prefix(words: &'static [&'static str], prefix: &[u8]) -> &'static [&'static str] {
+    let start = words.partition_point(|w| w.as_bytes() < prefix);
+    let len = words[start..].partition_point(|w| w.as_bytes().starts_with(prefix));
+    &words[start..start + len]
+}
+
+/// letter_mask implements next_letter_mask and next_letter_mask_13th_word.
+fn letter_mask(words: &'static [&'static str], prefix: &str) -> [bool; 26] {
+    let mut mask = [false; 26];
+    // No word is longer than MAX_PREFIX, so a prefix that long can't be extended.
+    if prefix.len() >= MAX_PREFIX {
+        return mask;
+    }
+    let mut lowered = [0u8; MAX_PREFIX];
+    lowered[..prefix.len()].copy_from_slice(prefix.as_bytes());
+    lowered.make_ascii_lowercase();
+    let prefix = &lowered[..prefix.len()];
+    for word in words_with_prefix(words, prefix) {
+        if let Some(&next) = word.as_bytes().get(prefix.len()) {
+            if next.is_ascii_lowercase() {
+                mask[(next - b'a') as usize] = true;
+            }
+        }
+    }
+    mask
+}
+
+/// next_letter_mask returns which of the letters 'a' through 'z' can follow 'prefix' in some
+/// dictionary word, so that an on-screen keyboard can disable the rest. The prefix is
+/// case-insensitive, like LetterEntry::push_letter. A prefix that is already a complete word with
+/// no longer words after it gets an all-false mask. It does not allocate and only scans the words
+/// that share the prefix.
+pub fn next_letter_mask(prefix: &str) -> [bool; 26] {
+    letter_mask(&DICTIONARY, prefix)
+}
+
+/// next_letter_mask_13th_word is next_letter_mask for the 13th word of a phrase, which must be one
+/// of the first 256 words of the dictionary.
+pub fn next_letter_mask_13th_word(prefix: &str) -> [bool; 26] {
+    letter_mask(&DICTIONARY[..256], prefix)
+}
+
+/// max_word_length returns the length of the longest dictionary word.
+pub fn max_word_length() -> usize {
+    DICTIONARY.iter().map(|w| w.len()).max().unwrap_or(0)
+}
+
+/// min_word_length returns the length of the shortest dictionary word.
+pub fn min_word_length() -> usize {
+    DICTIONARY.iter().map(|w| w.len()).min().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_to_seed_phrase;
+    use crate::random_seed;
+
+    #[test]
+    // Enter full phrases with as few keystrokes as possible and check the resulting seed.
+    fn check_letter_entry() {
+        for _ in 0..20 {
+            let seed = random_seed();
+            let phrase = seed_to_seed_phrase(seed);
+            let mut entry = LetterEntry::new();
+            let mut keystrokes = 0;
+            for word in phrase.split(' ') {
+                for c in word.chars() {
+                    keystrokes += 1;
+                    if let LetterEntryState::Unique(w) = entry.push_letter(c) {
+                        assert_eq!(w, word);
+                        break;
+                    }
+                }
+                entry.accept_word().unwrap();
+            }
+            assert!(keystrokes <= 3 * PHRASE_WORDS);
+            assert_eq!(entry.push_letter('a'), LetterEntryState::Rejected);
+            assert_eq!(entry.seed().unwrap(), seed);
+        }
+    }
+
+    #[test]
+    // Check candidate narrowing, the 13th word restriction, rejection, and backspace.
+    fn check_letter_entry_transitions() {
+        let mut entry = LetterEntry::new();
+        assert_eq!(entry.remaining_candidates().len(), 1024);
+        assert_eq!(entry.push_letter('Q'), LetterEntryState::Candidates(3));
+        assert_eq!(entry.push_letter('z'), LetterEntryState::Rejected);
+        entry.accept_word().unwrap_err();
+        entry.backspace();
+        assert_eq!(entry.remaining_candidates().len(), 1024);
+
+        // Move to the 13th word, which only has 256 candidates.
+        for _ in 0..SEED_ENTROPY_WORDS - 1 {
+            for c in "abb".chars() {
+                entry.push_letter(c);
+            }
+            entry.accept_word().unwrap();
+        }
+        assert_eq!(entry.position(), SEED_ENTROPY_WORDS - 1);
+        assert_eq!(entry.remaining_candidates().len(), 256);
+        assert_eq!(entry.push_letter('z'), LetterEntryState::Rejected);
+
+        // Backspacing an empty word returns to the previous position.
+        entry.backspace();
+        assert_eq!(entry.position(), SEED_ENTROPY_WORDS - 2);
+        assert_eq!(entry.remaining_candidates().len(), 1024);
+        entry.seed().unwrap_err();
+    }
+
+    #[test]
+    // Check the letter masks against a direct scan of the dictionary.
+    fn check_next_letter_mask() {
+        let letters = |mask: [bool; 26]| -> String {
+            (b'a'..=b'z')
+                .filter(|c| mask[(c - b'a') as usize])
+                .map(char::from)
+                .collect()
+        };
+
+        // The empty prefix enables exactly the first letters of the dictionary words.
+        let mut first: Vec<char> = DICTIONARY
+            .iter()
+            .map(|w| w.chars().next().unwrap())
+            .collect();
+        first.dedup();
+        assert_eq!(
+            letters(next_letter_mask("")),
+            first.into_iter().collect::<String>()
+        );
+
+        // Every word is a complete word that extends to nothing, or to longer words.
+        for word in DICTIONARY {
+            let extended = DICTIONARY
+                .iter()
+                .any(|w| w.len() > word.len() && w.starts_with(word));
+            assert_eq!(next_letter_mask(word).contains(&true), extended);
+        }
+        assert_eq!(letters(next_letter_mask("zz")), "");
+        assert_eq!(next_letter_mask("A"), next_letter_mask("a"));
+        assert_eq!(next_letter_mask("AbB"), next_letter_mask("abb"));
+        assert_eq!(
+            next_letter_mask_13th_word("A"),
+            next_letter_mask_13th_word("a")
+        );
+        assert!(next_letter_mask("A").contains(&true));
+        assert_eq!(letters(next_letter_mask("\u{e9}")), "");
+
+        // The 13th word is limited to the first 256 words, which stop partway through the words
+        // starting with the same letter as the 256th word.
+        let boundary = &DICTIONARY[255][..1];
+        let full = letters(next_letter_mask(""));
+        let limited = letters(next_letter_mask_13th_word(""));
+        assert!(full.len() > limited.len());
+        assert!(full.starts_with(&limited) && limited.ends_with(boundary));
+        assert_ne!(
+            next_letter_mask(boundary),
+            next_letter_mask_13th_word(boundary)
+        );
+        for prefix in [boundary, &DICTIONARY[255][..2], "a", "zo"] {
+            let mask = next_letter_mask_13th_word(prefix);
+            for (i, &enabled) in mask.iter().enumerate() {
+                let c = char::from(b'a' + i as u8);
+                let expected = DICTIONARY[..256]
+                    .iter()
+                    .any(|w| w.starts_with(&format!("{}{}", prefix, c)));
+                assert_eq!(enabled, expected);
+            }
+        }
+
+        assert_eq!(max_word_length(), MAX_PREFIX);
+        assert!(min_word_length() >= 3);
+        assert!(DICTIONARY.iter().any(|w| w.len() == min_word_length()));
+    }
+}
@@ -0,0 +1,210 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! epoch contains helpers for deriving keypairs that rotate over time. Time is divided into
+//! numbered epochs of a fixed length starting at an origin, and each epoch has its own keypair
+//! derived from the seed, so the keypair of any past epoch can be derived again to verify old
+//! signatures.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Error, Result};
+use ed25519_dalek::Keypair;
+
+use crate::derive::hash_fields;
+use crate::keypair::keypair_from_seed;
+use crate::Seed;
+
+/// EPOCH_SEED_SPECIFIER is the domain separator used when deriving epoch seeds.
+const EPOCH_SEED_SPECIFIER: &[u8] = b"seed15 epoch seed";
+
+/// EpochScheme divides time into epochs of a fixed length. Epoch n covers the half-open interval
+/// [origin + n * length, origin + (n + 1) * length), so an instant exactly on a boundary belongs to
+/// the later epoch. Epochs have a fixed length, so calendar months are approximated, for example
+/// with 30 days.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EpochScheme {
+    origin: SystemTime,
+    length: Duration,
+}
+
+impl EpochScheme {
+    /// new creates a scheme whose epoch 0 starts at 'origin'. The origin can't be before the unix
+    /// epoch and the length must be positive.
+    pub fn new(origin: SystemTime, length: Duration) -> Result<EpochScheme, Error> {
+        if origin < UNIX_EPOCH {
+            bail!("epoch origin can't be before the unix epoch");
+        }
+        if length.is_zero() {
+            bail!("epoch length must be positive");
+        }
+        Ok(EpochScheme { origin, length })
+    }
+
+    /// origin returns the start of epoch 0.
+    pub fn origin(&self) -> SystemTime {
+        self.origin
+    }
+
+    /// length returns the length of each epoch.
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    /// epoch_for returns the number of the epoch containing 'at'. An error is returned if 'at' is
+    /// before the origin.
+    pub fn epoch_for(&self, at: SystemTime) -> Result<u64, Error> {
+        let elapsed = match at.duration_since(self.origin) {
+            Ok(elapsed) => elapsed,
+            Err(_) => bail!("time is before the origin of the epoch scheme"),
+        };
+        let epoch = elapsed.as_nanos() / self.length.as_nanos();
+        match u64::try_from(epoch) {
+            Ok(epoch) => Ok(epoch),
+            Err(_) => bail!("epoch number does not fit in a u64"),
+        }
+    }
+
+    /// epoch_start returns the first instant of an epoch. An error is returned if the instant
+    /// can't be represented.
+    pub fn epoch_start(&self, epoch: u64) -> Result<SystemTime, Error> {
+        let offset = match self.length.as_nanos().checked_mul(epoch as u128) {
+            Some(offset) => offset,
+            None => bail!("epoch {} starts too far in the future", epoch),
+        };
+        let secs = match u64::try_from(offset / 1_000_000_000) {
+            Ok(secs) => secs,
+            Err(_) => bail!("epoch {} starts too far in the future", epoch),
+        };
+        let offset = Duration::new(secs, (offset % 1_000_000_000) as u32);
+        match self.origin.checked_add(offset) {
+            Some(start) => Ok(start),
+            None => bail!("epoch {} starts too far in the future", epoch),
+        }
+    }
+
+    /// encode returns the fixed length encoding of the scheme that is hashed into epoch seeds.
+    fn encode(&self) -> [u8; 24] {
+        let origin = self
+            .origin
+            .duration_since(UNIX_EPOCH)
+            .expect("origin is checked by new");
+        let mut encoded = [0u8; 24];
+        encoded[..8].copy_from_slice(&origin.as_secs().to_le_bytes());
+        encoded[8..12].copy_from_slice(&origin.subsec_nanos().to_le_bytes());
+        encoded[12..20].copy_from_slice(&self.length.as_secs().to_le_bytes());
+        encoded[20..].copy_from_slice(&self.length.subsec_nanos().to_le_bytes());
+        encoded
+    }
+}
+
+/// epoch_seed derives the seed for an epoch. The scheme's origin and length are part of the
+/// derivation, so changing either one changes every epoch seed.
+pub fn epoch_seed(seed: Seed, scheme: EpochScheme, epoch: u64) -> Seed {
+    let hash = hash_fields(&[
+        EPOCH_SEED_SPECIFIER,
+        &seed,
+        &scheme.encode(),
+        &epoch.to_le_bytes(),
+    ]);
+    let mut epoch_seed: Seed = [0u8; 16];
+    epoch_seed.copy_from_slice(&hash[..16]);
+    epoch_seed
+}
+
+/// keypair_for_epoch returns the keypair of an epoch, which is the keypair of its epoch seed.
+pub fn keypair_for_epoch(seed: Seed, scheme: EpochScheme, epoch: u64) -> Keypair {
+    keypair_from_seed(epoch_seed(seed, scheme, epoch))
+}
+
+/// epoch_keypair returns the number and keypair of the epoch containing 'at'. An error is returned
+/// if 'at' is before the origin of the scheme.
+pub fn epoch_keypair(
+    seed: Seed,
+    scheme: EpochScheme,
+    at: SystemTime,
+) -> Result<(u64, Keypair), Error> {
+    let epoch = scheme.epoch_for(at)?;
+    Ok((epoch, keypair_for_epoch(seed, scheme, epoch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_seed;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    // monthly returns a scheme of 30 day epochs starting at 2024-01-01.
+    fn monthly() -> EpochScheme {
+        EpochScheme::new(UNIX_EPOCH + Duration::from_secs(1_704_067_200), DAY * 30).unwrap()
+    }
+
+    #[test]
+    // Epochs are half-open, and times before the origin are rejected.
+    fn check_epoch_boundaries() {
+        let scheme = monthly();
+        let origin = scheme.origin();
+        let nano = Duration::from_nanos(1);
+        assert_eq!(scheme.epoch_for(origin).unwrap(), 0);
+        assert_eq!(scheme.epoch_for(origin + DAY * 30 - nano).unwrap(), 0);
+        assert_eq!(scheme.epoch_for(origin + DAY * 30).unwrap(), 1);
+        assert_eq!(scheme.epoch_for(origin + DAY * 300 + DAY).unwrap(), 10);
+        scheme.epoch_for(origin - nano).unwrap_err();
+        for epoch in [0, 1, 7, 1000] {
+            let start = scheme.epoch_start(epoch).unwrap();
+            assert_eq!(scheme.epoch_for(start).unwrap(), epoch);
+            if epoch > 0 {
+                assert_eq!(scheme.epoch_for(start - nano).unwrap(), epoch - 1);
+            }
+        }
+        scheme.epoch_start(u64::MAX).unwrap_err();
+        let longest = EpochScheme::new(UNIX_EPOCH, Duration::MAX).unwrap();
+        longest.epoch_start(u64::MAX).unwrap_err();
+
+        EpochScheme::new(origin, Duration::ZERO).unwrap_err();
+        EpochScheme::new(UNIX_EPOCH - nano, DAY).unwrap_err();
+        let tiny = EpochScheme::new(UNIX_EPOCH, nano).unwrap();
+        tiny.epoch_for(UNIX_EPOCH + DAY * 365 * 1000).unwrap_err();
+    }
+
+    #[test]
+    // Keypairs are stable within an epoch and depend on the epoch and on the scheme.
+    fn check_epoch_keypair() {
+        let seed = random_seed();
+        let scheme = monthly();
+        let origin = scheme.origin();
+        let (epoch, keypair) = epoch_keypair(seed, scheme, origin + DAY * 45).unwrap();
+        assert_eq!(epoch, 1);
+        assert_eq!(
+            keypair.to_bytes(),
+            keypair_for_epoch(seed, scheme, 1).to_bytes()
+        );
+        let (_, same) = epoch_keypair(seed, scheme, origin + DAY * 59).unwrap();
+        assert_eq!(keypair.to_bytes(), same.to_bytes());
+        let (_, next) = epoch_keypair(seed, scheme, origin + DAY * 60).unwrap();
+        assert_ne!(keypair.to_bytes(), next.to_bytes());
+        assert!(epoch_keypair(seed, scheme, origin - DAY).is_err());
+
+        // Every scheme parameter is part of the derivation.
+        let shifted = EpochScheme::new(origin + Duration::from_nanos(1), DAY * 30).unwrap();
+        let longer = EpochScheme::new(origin, DAY * 31).unwrap();
+        let subsec = EpochScheme::new(origin, DAY * 30 + Duration::from_nanos(1)).unwrap();
+        let seeds = [
+            epoch_seed(seed, scheme, 1),
+            epoch_seed(seed, shifted, 1),
+            epoch_seed(seed, longer, 1),
+            epoch_seed(seed, subsec, 1),
+            epoch_seed(seed, scheme, 2),
+            epoch_seed(random_seed(), scheme, 1),
+        ];
+        for i in 0..seeds.len() {
+            for j in i + 1..seeds.len() {
+                assert_ne!(seeds[i], seeds[j]);
+            }
+        }
+        assert_ne!(epoch_seed(seed, scheme, 1), seed);
+    }
+}
@@ -0,0 +1,133 @@
+#![allow(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! ffi exposes a C ABI for generating seeds and converting between seeds and seed phrases. It is
+//! only available with the 'ffi' feature.
+//!
+//! All buffers are owned by the caller. Seeds are always 16 bytes. Phrases are passed as NUL
+//! terminated UTF-8 strings; a buffer of SEED15_PHRASE_CAPACITY bytes is always large enough to
+//! hold a phrase and its terminator.
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use crate::phrase::{seed_phrase_to_seed, seed_to_seed_phrase};
+use crate::{random_seed, Seed};
+
+/// SEED15_PHRASE_CAPACITY is the number of bytes needed to hold any seed phrase, including the NUL
+/// terminator: 15 words of at most 6 characters, 14 spaces, and the terminator.
+pub const SEED15_PHRASE_CAPACITY: usize = 15 * 6 + 14 + 1;
+
+/// SEED15_OK indicates that the call succeeded.
+pub const SEED15_OK: c_int = 0;
+/// SEED15_ERR_NULL_POINTER indicates that a required pointer was null.
+pub const SEED15_ERR_NULL_POINTER: c_int = -1;
+/// SEED15_ERR_BUFFER_TOO_SMALL indicates that the output buffer cannot hold the result.
+pub const SEED15_ERR_BUFFER_TOO_SMALL: c_int = -2;
+/// SEED15_ERR_INVALID_UTF8 indicates that the input string was not valid UTF-8.
+pub const SEED15_ERR_INVALID_UTF8: c_int = -3;
+/// SEED15_ERR_INVALID_PHRASE indicates that the input was not a valid seed phrase.
+pub const SEED15_ERR_INVALID_PHRASE: c_int = -4;
+
+/// seed15_random writes a new random 16 byte seed to 'out'. Nothing is written if 'out' is null.
+///
+/// # Safety
+///
+/// 'out' must be null or point to at least 16 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn seed15_random(out: *mut u8) {
+    if out.is_null() {
+        return;
+    }
+    let seed = random_seed();
+    ptr::copy_nonoverlapping(seed.as_ptr(), out, seed.len());
+}
+
+/// seed15_to_phrase writes the seed phrase for the 16 byte seed at 'seed' into 'out' as a NUL
+/// terminated string. 'cap' is the size of 'out' in bytes, and must be at least the length of the
+/// phrase plus one. SEED15_PHRASE_CAPACITY is always sufficient.
+///
+/// # Safety
+///
+/// 'seed' must be null or point to 16 readable bytes, and 'out' must be null or point to 'cap'
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn seed15_to_phrase(seed: *const u8, out: *mut c_char, cap: usize) -> c_int {
+    if seed.is_null() || out.is_null() {
+        return SEED15_ERR_NULL_POINTER;
+    }
+    let mut s: Seed = [0u8; 16];
+    ptr::copy_nonoverlapping(seed, s.as_mut_ptr(), s.len());
+    let phrase = seed_to_seed_phrase(s);
+    if phrase.len() + 1 > cap {
+        return SEED15_ERR_BUFFER_TOO_SMALL;
+    }
+    ptr::copy_nonoverlapping(phrase.as_ptr() as *const c_char, out, phrase.len());
+    *out.add(phrase.len()) = 0;
+    SEED15_OK
+}
+
+/// seed15_phrase_to_seed parses the NUL terminated seed phrase at 'phrase' and writes the 16 byte
+/// seed to 'out'. Nothing is written to 'out' unless SEED15_OK is returned.
+///
+/// # Safety
+///
+/// 'phrase' must be null or point to a NUL terminated string, and 'out' must be null or point to
+/// 16 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn seed15_phrase_to_seed(phrase: *const c_char, out: *mut u8) -> c_int {
+    if phrase.is_null() || out.is_null() {
+        return SEED15_ERR_NULL_POINTER;
+    }
+    let phrase = match CStr::from_ptr(phrase).to_str() {
+        Ok(p) => p,
+        Err(_) => return SEED15_ERR_INVALID_UTF8,
+    };
+    let seed = match seed_phrase_to_seed(phrase) {
+        Ok(s) => s,
+        Err(_) => return SEED15_ERR_INVALID_PHRASE,
+    };
+    ptr::copy_nonoverlapping(seed.as_ptr(), out, seed.len());
+    SEED15_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    // Round-trip a seed through the C ABI and check the error codes.
+    fn check_ffi() {
+        unsafe {
+            let mut seed = [0u8; 16];
+            seed15_random(seed.as_mut_ptr());
+
+            let mut buf = [0 as c_char; SEED15_PHRASE_CAPACITY];
+            let r = seed15_to_phrase(seed.as_ptr(), buf.as_mut_ptr(), buf.len());
+            assert_eq!(r, SEED15_OK);
+            let phrase = CStr::from_ptr(buf.as_ptr()).to_str().unwrap();
+            assert_eq!(phrase, seed_to_seed_phrase(seed));
+
+            let mut out = [0u8; 16];
+            let r = seed15_phrase_to_seed(buf.as_ptr(), out.as_mut_ptr());
+            assert_eq!(r, SEED15_OK);
+            assert_eq!(out, seed);
+
+            // Error cases.
+            let r = seed15_to_phrase(seed.as_ptr(), buf.as_mut_ptr(), phrase.len());
+            assert_eq!(r, SEED15_ERR_BUFFER_TOO_SMALL);
+            let r = seed15_to_phrase(ptr::null(), buf.as_mut_ptr(), buf.len());
+            assert_eq!(r, SEED15_ERR_NULL_POINTER);
+            let bad = CString::new("abbey abbey").unwrap();
+            let r = seed15_phrase_to_seed(bad.as_ptr(), out.as_mut_ptr());
+            assert_eq!(r, SEED15_ERR_INVALID_PHRASE);
+            let bad = CString::new(vec![0xffu8, 0xfe]).unwrap();
+            let r = seed15_phrase_to_seed(bad.as_ptr(), out.as_mut_ptr());
+            assert_eq!(r, SEED15_ERR_INVALID_UTF8);
+        }
+    }
+}
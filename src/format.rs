@@ -0,0 +1,230 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! format contains helpers for writing seed phrases with separators other than a space, for
+//! mediums such as engraving plates or form fields that can't hold spaces.
+
+use anyhow::{bail, Error, Result};
+use dictionary_1024::{DICTIONARY, DICTIONARY_UNIQUE_PREFIX};
+
+use crate::phrase::{
+    seed_phrase_to_seed, seed_to_seed_phrase, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS,
+};
+use crate::Seed;
+
+/// Separator describes how the words of a phrase are joined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Separator {
+    /// Space separates words with a single space, which is the standard format.
+    #[default]
+    Space,
+    /// Dash separates words with a single '-'.
+    Dash,
+    /// Newline puts each word on its own line.
+    Newline,
+    /// CamelCase uses no separator and capitalizes the first letter of each word, for example
+    /// "AbbeyAbleAbort".
+    CamelCase,
+}
+
+/// PhraseFormat describes how a seed phrase is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhraseFormat {
+    /// separator is the separator placed between words.
+    pub separator: Separator,
+}
+
+/// seed_to_seed_phrase_with_format converts a seed to a seed phrase written in the given format.
+pub fn seed_to_seed_phrase_with_format(seed: Seed, format: PhraseFormat) -> String {
+    let phrase = seed_to_seed_phrase(seed);
+    let words = phrase.split(' ');
+    match format.separator {
+        Separator::Space => phrase,
+        Separator::Dash => words.collect::<Vec<&str>>().join("-"),
+        Separator::Newline => words.collect::<Vec<&str>>().join("\n"),
+        Separator::CamelCase => words
+            .map(|w| w[..1].to_uppercase() + &w[1..])
+            .collect::<String>(),
+    }
+}
+
+/// seed_phrase_to_seed_with_format parses a seed phrase written in the given format.
+///
+/// CamelCase phrases are split using the dictionary rather than the capital letters, so a phrase
+/// that lost its capitalization can still be parsed. Every dictionary word is identified by its
+/// first three letters and no dictionary word is a prefix of another, so there is only ever one
+/// way to split a phrase into dictionary words. Words must be spelled out in full.
+pub fn seed_phrase_to_seed_with_format(phrase: &str, format: PhraseFormat) -> Result<Seed, Error> {
+    match format.separator {
+        Separator::Space => seed_phrase_to_seed(phrase),
+        Separator::Dash => seed_phrase_to_seed(&phrase.split('-').collect::<Vec<&str>>().join(" ")),
+        Separator::Newline => {
+            let words: Vec<&str> = phrase.lines().collect();
+            seed_phrase_to_seed(&words.join(" "))
+        }
+        Separator::CamelCase => seed_phrase_to_seed(&segment_words(phrase)?.join(" ")),
+    }
+}
+
+/// seed_to_word_grid lays the words of a seed phrase out in rows of 'cols' words, for interfaces
+/// that show the phrase as a matrix. The words read left to right and then top to bottom, and the
+/// last row is padded with empty strings when 'cols' does not divide 15. The column count is
+/// clamped to between 1 and 15, so zero is treated as one and anything wider than the phrase gives
+/// a single row of 15 words.
+pub fn seed_to_word_grid(seed: Seed, cols: usize) -> Vec<Vec<String>> {
+    let cols = cols.clamp(1, SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS);
+    let phrase = seed_to_seed_phrase(seed);
+    let words: Vec<&str> = phrase.split(' ').collect();
+    words
+        .chunks(cols)
+        .map(|row| {
+            let mut row: Vec<String> = row.iter().map(|w| w.to_string()).collect();
+            row.resize(cols, String::new());
+            row
+        })
+        .collect()
+}
+
+/// word_grid_to_seed reads a grid produced by seed_to_word_grid back into a seed. Empty cells are
+/// skipped, so the padding in the last row is ignored.
+pub fn word_grid_to_seed(grid: &[Vec<String>]) -> Result<Seed, Error> {
+    let words: Vec<&str> = grid
+        .iter()
+        .flatten()
+        .map(|w| w.as_str())
+        .filter(|w| !w.is_empty())
+        .collect();
+    seed_phrase_to_seed(&words.join(" "))
+}
+
+/// segment_words splits a string of concatenated dictionary words into the individual words.
+fn segment_words(text: &str) -> Result<Vec<&'static str>, Error> {
+    if !text.is_ascii() {
+        bail!("phrase contains non-ascii characters");
+    }
+    let text = text.to_ascii_lowercase();
+    let mut words = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        if rest.len() < DICTIONARY_UNIQUE_PREFIX {
+            bail!("trailing characters '{}' are not a dictionary word", rest);
+        }
+        let word = match DICTIONARY
+            .iter()
+            .find(|w| w[..DICTIONARY_UNIQUE_PREFIX] == rest[..DICTIONARY_UNIQUE_PREFIX])
+        {
+            Some(w) => w,
+            None => bail!(
+                "no dictionary word starts with '{}' at offset {}",
+                &rest[..DICTIONARY_UNIQUE_PREFIX],
+                pos
+            ),
+        };
+        if !rest.starts_with(word) {
+            bail!("expected the word '{}' at offset {}", word, pos);
+        }
+        words.push(*word);
+        pos += word.len();
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_seed;
+
+    const SEPARATORS: [Separator; 4] = [
+        Separator::Space,
+        Separator::Dash,
+        Separator::Newline,
+        Separator::CamelCase,
+    ];
+
+    #[test]
+    // Every separator should round-trip for many random seeds.
+    fn check_format_round_trip() {
+        for _ in 0..1000 {
+            let seed = random_seed();
+            for separator in SEPARATORS {
+                let format = PhraseFormat { separator };
+                let phrase = seed_to_seed_phrase_with_format(seed, format);
+                assert_eq!(
+                    seed_phrase_to_seed_with_format(&phrase, format).unwrap(),
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Check the CamelCase rendering and the segmentation edge cases.
+    fn check_camel_case() {
+        let format = PhraseFormat {
+            separator: Separator::CamelCase,
+        };
+        let phrase = seed_to_seed_phrase_with_format([0u8; 16], format);
+        assert_eq!(
+            phrase,
+            "AbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyAbbeyDonutBevel"
+        );
+
+        // Capitalization is not needed to split the phrase.
+        let lower = phrase.to_lowercase();
+        assert_eq!(
+            seed_phrase_to_seed_with_format(&lower, format).unwrap(),
+            [0u8; 16]
+        );
+
+        // The segmentation relies on no word being a prefix of another word.
+        for a in DICTIONARY {
+            for b in DICTIONARY {
+                assert!(a == b || !b.starts_with(a));
+            }
+        }
+
+        // Abbreviated words, truncated phrases, and garbage are rejected.
+        seed_phrase_to_seed_with_format(&phrase.replacen("Abbey", "Abb", 1), format).unwrap_err();
+        seed_phrase_to_seed_with_format(&phrase[..phrase.len() - 2], format).unwrap_err();
+        seed_phrase_to_seed_with_format(&format!("{}Abbey", phrase), format).unwrap_err();
+        seed_phrase_to_seed_with_format("Zzzzz", format).unwrap_err();
+        seed_phrase_to_seed_with_format("Abb\u{e9}y", format).unwrap_err();
+    }
+
+    #[test]
+    // Check the grid dimensions and that grids read back into the same seed.
+    fn check_word_grid() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        for (cols, rows, padding) in [(3, 5, 0), (5, 3, 0), (4, 4, 1), (1, 15, 0), (15, 1, 0)] {
+            let grid = seed_to_word_grid(seed, cols);
+            assert_eq!(grid.len(), rows);
+            assert!(grid.iter().all(|row| row.len() == cols));
+            let words: Vec<&String> = grid.iter().flatten().collect();
+            assert_eq!(words.iter().filter(|w| w.is_empty()).count(), padding);
+            assert_eq!(word_grid_to_seed(&grid).unwrap(), seed);
+        }
+
+        // Words read left to right and then top to bottom.
+        let grid = seed_to_word_grid(seed, 3);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        assert_eq!(grid[1][2], words[5]);
+        assert_eq!(grid[4][0], words[12]);
+        assert_eq!(seed_to_word_grid(seed, 0), seed_to_word_grid(seed, 1));
+        assert_eq!(seed_to_word_grid(seed, 16), seed_to_word_grid(seed, 15));
+        assert_eq!(
+            seed_to_word_grid(seed, usize::MAX),
+            seed_to_word_grid(seed, 15)
+        );
+
+        let mut swapped = grid.clone();
+        swapped.swap(0, 1);
+        if swapped != grid {
+            word_grid_to_seed(&swapped).unwrap_err();
+        }
+        word_grid_to_seed(&grid[..4]).unwrap_err();
+    }
+}
@@ -1,4 +1,5 @@
-#![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "ffi"), forbid(unsafe_code))]
+#![cfg_attr(feature = "ffi", deny(unsafe_code))]
 #![deny(missing_docs)]
 #![deny(unused_must_use)]
 #![deny(unused_mut)]
@@ -37,8 +38,42 @@
 //! A full specification of the seed protocol can be found here:
 //! https://blog.sia.tech/a-technical-breakdown-of-mysky-seeds-ba9964505978
 
+pub mod airgap;
+pub mod attest;
+pub mod audit;
+pub mod canonical;
+pub mod confirm;
+pub mod denylist;
+pub mod derive;
+pub mod detect;
+pub mod dictionary;
+pub mod ecc;
+pub mod entry;
+pub mod epoch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
 pub mod keypair;
+pub mod normalize;
+pub mod obfuscate;
+pub mod ocr;
+pub mod pack;
 pub mod phrase;
+pub mod qr;
+#[cfg(any(test, feature = "ratelimit"))]
+pub mod ratelimit;
+pub mod repair;
+pub mod session;
+pub mod split;
+pub mod ssh;
+#[cfg(all(unix, any(test, feature = "ssh-agent")))]
+pub mod ssh_agent;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testutils;
+#[cfg(any(test, feature = "verify"))]
+pub mod verify;
 
 use userspace_rng::random256;
 
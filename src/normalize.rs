@@ -0,0 +1,213 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! normalize contains helpers for cleaning up seed phrases that were pasted from documents and
+//! chat apps. Such phrases often contain invisible formatting characters, which otherwise surface
+//! as confusing "unknown word" errors for words that look correct.
+
+use std::fmt;
+
+/// INVISIBLE_CHARACTERS lists the formatting characters that are removed from a phrase before it
+/// is split into words: zero width space, zero width non-joiner, zero width joiner, word joiner,
+/// byte order mark, and soft hyphen.
+pub const INVISIBLE_CHARACTERS: [char; 6] = [
+    '\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}', '\u{feff}', '\u{ad}',
+];
+
+/// SPACE_CHARACTERS lists the characters that are treated as a plain space: no-break space and
+/// narrow no-break space.
+pub const SPACE_CHARACTERS: [char; 2] = ['\u{a0}', '\u{202f}'];
+
+/// MAX_INPUT_LEN is the longest input, in bytes, that the normalizer and the seed phrase parsers
+/// will look at. A seed phrase is at most a few hundred bytes, so anything longer is rejected
+/// before it is tokenized, which bounds the time and memory spent on hostile input.
+pub const MAX_INPUT_LEN: usize = 64 * 1024;
+
+/// InputError describes input that can't be normalized into a phrase. Positions are byte offsets
+/// into the original input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// InvisibleCharacters lists the positions of invisible characters. It is only returned by
+    /// normalize_phrase_strict; normalize_phrase removes them instead.
+    InvisibleCharacters {
+        /// positions holds the byte offset of every invisible character.
+        positions: Vec<usize>,
+    },
+    /// ControlCharacter indicates a control character other than whitespace, such as NUL.
+    ControlCharacter {
+        /// position is the byte offset of the first control character.
+        position: usize,
+    },
+    /// InputTooLarge indicates that the input is longer than MAX_INPUT_LEN.
+    InputTooLarge {
+        /// len is the length of the input in bytes.
+        len: usize,
+    },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::InvisibleCharacters { positions } => write!(
+                f,
+                "phrase contains invisible characters at byte offsets {:?}",
+                positions
+            ),
+            InputError::ControlCharacter { position } => {
+                write!(
+                    f,
+                    "phrase contains a control character at byte offset {}",
+                    position
+                )
+            }
+            InputError::InputTooLarge { len } => write!(
+                f,
+                "input is {} bytes, which is longer than the limit of {} bytes",
+                len, MAX_INPUT_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// normalize_phrase prepares pasted text for seed_phrase_to_seed. Invisible characters are
+/// removed, no-break spaces are treated as spaces, the text is lowercased, and the words are
+/// joined by single spaces. Control characters other than whitespace are always an error and are
+/// never passed on to the dictionary.
+pub fn normalize_phrase(input: &str) -> Result<String, InputError> {
+    normalize(input, false)
+}
+
+/// normalize_phrase_strict is like normalize_phrase, but returns an error listing the invisible
+/// characters instead of removing them, for apps that want to show the user exactly what was
+/// wrong with the input.
+pub fn normalize_phrase_strict(input: &str) -> Result<String, InputError> {
+    normalize(input, true)
+}
+
+/// normalize implements normalize_phrase and normalize_phrase_strict.
+fn normalize(input: &str, strict: bool) -> Result<String, InputError> {
+    if input.len() > MAX_INPUT_LEN {
+        return Err(InputError::InputTooLarge { len: input.len() });
+    }
+    let mut cleaned = String::with_capacity(input.len());
+    let mut invisible = Vec::new();
+    for (i, c) in input.char_indices() {
+        if INVISIBLE_CHARACTERS.contains(&c) {
+            invisible.push(i);
+        } else if SPACE_CHARACTERS.contains(&c) {
+            cleaned.push(' ');
+        } else if c.is_control() && !c.is_whitespace() {
+            return Err(InputError::ControlCharacter { position: i });
+        } else {
+            cleaned.push(c);
+        }
+    }
+    if strict && !invisible.is_empty() {
+        return Err(InputError::InvisibleCharacters {
+            positions: invisible,
+        });
+    }
+    let lower = cleaned.to_lowercase();
+    let mut normalized = String::with_capacity(lower.len());
+    for word in lower.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.push_str(word);
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::{seed_phrase_to_seed, seed_to_seed_phrase};
+    use crate::random_seed;
+
+    #[test]
+    // Check pasted phrases with each kind of invisible character.
+    fn check_normalize_phrase() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let check = |input: &str, positions: Vec<usize>| {
+            let normalized = normalize_phrase(input).unwrap();
+            assert_eq!(seed_phrase_to_seed(&normalized).unwrap(), seed);
+            let strict = normalize_phrase_strict(input);
+            if positions.is_empty() {
+                assert_eq!(strict.unwrap(), normalized);
+            } else {
+                assert_eq!(strict, Err(InputError::InvisibleCharacters { positions }));
+            }
+        };
+
+        // Zero width space between letters of the first word.
+        check(
+            &format!("{}\u{200b}{}", &phrase[..2], &phrase[2..]),
+            vec![2],
+        );
+
+        // No-break spaces between words are not invisible, they are just spaces.
+        check(&phrase.replace(' ', "\u{a0}"), vec![]);
+
+        // Byte order mark at the start, and a soft hyphen inside a later word.
+        let offset = words[0].len() + 1 + 2;
+        let hyphenated = format!("\u{feff}{}\u{ad}{}", &phrase[..offset], &phrase[offset..]);
+        check(&hyphenated, vec![0, offset + 3]);
+
+        // Case and repeated whitespace.
+        check(
+            &format!("  {}\n", phrase.to_uppercase().replace(' ', "\t ")),
+            vec![],
+        );
+
+        // Control characters are always an error.
+        let nul = format!("{}\u{0}{}", &phrase[..4], &phrase[4..]);
+        assert_eq!(
+            normalize_phrase(&nul),
+            Err(InputError::ControlCharacter { position: 4 })
+        );
+        assert_eq!(
+            normalize_phrase_strict("\u{7}abbey"),
+            Err(InputError::ControlCharacter { position: 0 })
+        );
+    }
+
+    #[test]
+    // Hostile input is rejected by size before it is tokenized, and input up to the limit fails for
+    // the expected reasons.
+    fn check_adversarial_input() {
+        let check_rejected = |input: &str| {
+            let too_large = InputError::InputTooLarge { len: input.len() };
+            assert_eq!(normalize_phrase(input), Err(too_large.clone()));
+            assert_eq!(normalize_phrase_strict(input), Err(too_large.clone()));
+            let err = seed_phrase_to_seed(input).unwrap_err();
+            assert_eq!(err.downcast_ref::<InputError>(), Some(&too_large));
+        };
+        check_rejected(&"a ".repeat(100_000));
+        check_rejected(&" ".repeat(1 << 20));
+        check_rejected(&"a".repeat(1 << 20));
+        check_rejected(&"\u{200b}".repeat(MAX_INPUT_LEN));
+        check_rejected(&"a".repeat(MAX_INPUT_LEN + 1));
+
+        // The size is checked before any character is looked at: a control character that would
+        // otherwise be reported first does not change the error.
+        check_rejected(&format!("\u{7}{}", "a".repeat(MAX_INPUT_LEN)));
+
+        // Input up to the limit is tokenized, and fails for the expected reasons.
+        let tokens = "a ".repeat(MAX_INPUT_LEN / 2);
+        assert_eq!(normalize_phrase(&tokens).unwrap().len(), tokens.len() - 1);
+        let err = seed_phrase_to_seed(&tokens).unwrap_err().to_string();
+        assert!(err.contains("expecting 15 words"));
+        let near_word = "abbeyx".repeat(MAX_INPUT_LEN / 6 / 15);
+        let near_phrase = vec![near_word.as_str(); 15].join(" ");
+        assert!(near_phrase.len() <= MAX_INPUT_LEN);
+        seed_phrase_to_seed(&near_phrase).unwrap_err();
+        seed_phrase_to_seed(&" ".repeat(MAX_INPUT_LEN)).unwrap_err();
+        seed_phrase_to_seed(&"a".repeat(MAX_INPUT_LEN)).unwrap_err();
+    }
+}
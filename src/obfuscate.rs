@@ -0,0 +1,135 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! obfuscate contains a reversible scrambling of seed phrases for showing them on a shared
+//! screen. The scrambled phrase is made of dictionary words, so it looks like a phrase to anyone
+//! glancing at it, but it is not the user's phrase.
+//!
+//! This is NOT encryption. PINs are short and the phrase checksum tells an attacker when a PIN
+//! is right, so anyone holding a screenshot or recording of the scrambled phrase can try every
+//! 6 digit PIN in well under a second. It only protects against someone memorizing or jotting
+//! down words they catch sight of.
+
+use anyhow::{bail, Error, Result};
+use dictionary_1024::DICTIONARY;
+use sha2::{Digest, Sha256};
+
+use crate::derive::shuffle_indices;
+use crate::dictionary::index_of;
+use crate::phrase::{seed_phrase_to_seed, seed_to_seed_phrase};
+use crate::Seed;
+
+/// OBFUSCATE_SPECIFIER is the domain separator used when deriving the scrambling key from a PIN.
+const OBFUSCATE_SPECIFIER: &[u8] = b"seed15 obfuscate";
+
+/// Scramble holds the word positions and word substitution derived from a PIN.
+struct Scramble {
+    /// positions[i] is the position in the original phrase of scrambled word i.
+    positions: Vec<usize>,
+    /// substitution is a permutation of the dictionary indices.
+    substitution: Vec<usize>,
+}
+
+impl Scramble {
+    /// new derives the scramble for a phrase of 'n' words.
+    fn new(pin: &str, n: usize) -> Scramble {
+        let mut hasher = Sha256::new();
+        hasher.update(OBFUSCATE_SPECIFIER);
+        hasher.update(pin.as_bytes());
+        let mut key: Seed = [0u8; 16];
+        key.copy_from_slice(&hasher.finalize()[..16]);
+        Scramble {
+            positions: shuffle_indices(key, "positions", n),
+            substitution: shuffle_indices(key, "words", DICTIONARY.len()),
+        }
+    }
+}
+
+/// obfuscate_phrase scrambles a seed phrase using a PIN. The words are reordered and each word is
+/// replaced by a different dictionary word that depends on the PIN and its position, so repeated
+/// words don't stand out. Words that aren't in the dictionary can't be substituted and are only
+/// moved. See the module documentation for what this does and does not protect against.
+pub fn obfuscate_phrase(phrase: &str, pin: &str) -> String {
+    let words: Vec<&str> = phrase.split(' ').collect();
+    let scramble = Scramble::new(pin, words.len());
+    let scrambled: Vec<&str> = scramble
+        .positions
+        .iter()
+        .map(|&pos| match index_of(words[pos]) {
+            Some(index) => {
+                let shifted = (index as usize + pos) % DICTIONARY.len();
+                DICTIONARY[scramble.substitution[shifted]]
+            }
+            None => words[pos],
+        })
+        .collect();
+    scrambled.join(" ")
+}
+
+/// deobfuscate_phrase reverses obfuscate_phrase and returns the phrase in canonical form. An error
+/// is returned if the result is not a valid seed phrase, which almost always means the PIN is
+/// wrong.
+pub fn deobfuscate_phrase(obf: &str, pin: &str) -> Result<String, Error> {
+    let scrambled: Vec<&str> = obf.split(' ').collect();
+    let scramble = Scramble::new(pin, scrambled.len());
+    let mut unsubstitute = vec![0usize; DICTIONARY.len()];
+    for (i, &s) in scramble.substitution.iter().enumerate() {
+        unsubstitute[s] = i;
+    }
+
+    let mut words = vec![""; scrambled.len()];
+    for (&word, &pos) in scrambled.iter().zip(scramble.positions.iter()) {
+        words[pos] = match index_of(word) {
+            Some(index) => {
+                let shifted = unsubstitute[index as usize];
+                let n = DICTIONARY.len();
+                DICTIONARY[(shifted + n - pos % n) % n]
+            }
+            None => word,
+        };
+    }
+    let seed = match seed_phrase_to_seed(&words.join(" ")) {
+        Ok(seed) => seed,
+        Err(_) => bail!("deobfuscated phrase is not valid, the PIN is probably wrong"),
+    };
+    Ok(seed_to_seed_phrase(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random_seed;
+
+    #[test]
+    // Round trip phrases through obfuscation, and check that a wrong PIN fails.
+    fn check_obfuscate_phrase() {
+        for _ in 0..20 {
+            let phrase = seed_to_seed_phrase(random_seed());
+            let obf = obfuscate_phrase(&phrase, "4821");
+            assert_ne!(obf, phrase);
+            assert_eq!(obf, obfuscate_phrase(&phrase, "4821"));
+            assert_ne!(obf, obfuscate_phrase(&phrase, "4822"));
+            assert_eq!(obf.split(' ').count(), 15);
+            for word in obf.split(' ') {
+                assert!(DICTIONARY.contains(&word));
+            }
+            assert_eq!(deobfuscate_phrase(&obf, "4821").unwrap(), phrase);
+            deobfuscate_phrase(&obf, "4822").unwrap_err();
+            deobfuscate_phrase(&obf, "").unwrap_err();
+        }
+
+        // Repeated words are scrambled into different words.
+        let repeated = [DICTIONARY[0]; 15].join(" ");
+        let obf = obfuscate_phrase(&repeated, "1");
+        let first = obf.split(' ').next().unwrap();
+        assert!(obf.split(' ').any(|w| w != first));
+
+        // Abbreviated words come back in canonical form.
+        let phrase = seed_to_seed_phrase(random_seed());
+        let short: Vec<&str> = phrase.split(' ').map(|w| &w[..3]).collect();
+        let obf = obfuscate_phrase(&short.join(" "), "pin");
+        assert_eq!(deobfuscate_phrase(&obf, "pin").unwrap(), phrase);
+    }
+}
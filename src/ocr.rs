@@ -0,0 +1,541 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! ocr contains helpers for recovering a seed phrase from the OCR output of a photographed backup
+//! sheet. OCR output mixes up similar looking characters, such as '0' and 'o' or "rn" and "m", and
+//! picks up numbering and other layout noise. The checksum is used to decide between the possible
+//! readings of the text.
+
+use std::collections::HashSet;
+
+use dictionary_1024::DICTIONARY;
+
+use crate::detect::valid_indices;
+use crate::dictionary::index_of;
+use crate::normalize::MAX_INPUT_LEN;
+use crate::phrase::{entropy_indices_to_seed, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS};
+use crate::repair::most_likely_error_position;
+use crate::Seed;
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// OCR_CONFUSIONS lists the substitutions that are tried on a token that isn't a dictionary word.
+/// Each pair is (text as read, text as written).
+pub const OCR_CONFUSIONS: [(&str, &str); 14] = [
+    ("0", "o"),
+    ("1", "l"),
+    ("1", "i"),
+    ("|", "l"),
+    ("5", "s"),
+    ("8", "b"),
+    ("rn", "m"),
+    ("m", "rn"),
+    ("cl", "d"),
+    ("vv", "w"),
+    ("l", "i"),
+    ("i", "l"),
+    ("c", "e"),
+    ("e", "c"),
+];
+
+/// MAX_TOKEN_SUBSTITUTIONS is the largest number of substitutions applied to a single token.
+const MAX_TOKEN_SUBSTITUTIONS: usize = 3;
+
+/// MAX_TOKEN_LEN is the length above which a token is treated as noise rather than as a word that
+/// needs substitutions.
+const MAX_TOKEN_LEN: usize = 12;
+
+/// MAX_READINGS caps the number of readings checked per window of 15 tokens, which bounds the
+/// work done for very noisy text.
+const MAX_READINGS: usize = 1 << 16;
+
+/// MAX_TOTAL_READINGS caps the number of readings checked across all windows.
+const MAX_TOTAL_READINGS: usize = 1 << 20;
+
+/// MAX_RANKED_MISSES is the number of invalid readings with the fewest substitutions that are
+/// checked for a single-word repair when ranking near misses.
+const MAX_RANKED_MISSES: usize = 16;
+
+/// MAX_NEAR_MISSES is the largest number of candidates returned in OcrParseResult::NearMisses.
+const MAX_NEAR_MISSES: usize = 5;
+
+/// OcrParseResult is the result of parsing OCR text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OcrParseResult {
+    /// Confident indicates that exactly one of the readings with the fewest substitutions has a
+    /// valid checksum.
+    Confident {
+        /// seed is the seed of the phrase.
+        seed: Seed,
+        /// phrase is the phrase in canonical form.
+        phrase: String,
+        /// corrected lists the positions of the words that needed substitutions.
+        corrected: Vec<usize>,
+    },
+    /// NearMisses lists the best readings, ranked by validity and then by the number of
+    /// substitutions, when no single reading stands out. Either several readings have a valid
+    /// checksum, or none do.
+    NearMisses(Vec<OcrCandidate>),
+    /// Failed indicates that the text doesn't contain 15 recognizable words, and describes every
+    /// token that was considered.
+    Failed(Vec<TokenDiagnostic>),
+}
+
+/// OcrCandidate is one reading of the OCR text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OcrCandidate {
+    /// phrase is the reading in canonical form.
+    pub phrase: String,
+    /// substitutions is the total number of substitutions made.
+    pub substitutions: usize,
+    /// valid is true if the reading has a valid checksum.
+    pub valid: bool,
+    /// uncertain_positions lists the positions of words that needed substitutions or could be read
+    /// more than one way, and for invalid readings the position most likely to be wrong.
+    pub uncertain_positions: Vec<usize>,
+}
+
+/// TokenDiagnostic describes how a single token of the OCR text was read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenDiagnostic {
+    /// token is the lowercased token.
+    pub token: String,
+    /// candidates lists the dictionary words the token could be, with the number of substitutions
+    /// needed for each. It is empty if the token was not recognized.
+    pub candidates: Vec<(String, usize)>,
+}
+
+/// from_ocr_text parses the OCR output of a backup sheet. The text is split into tokens of letters,
+/// digits, and '|', and tokens that are only digits or are shorter than 3 characters are dropped
+/// as numbering and layout noise. A token that is a dictionary word, or the 3 letter prefix of
+/// one, is read as that word. Other tokens are rewritten with up to 3 substitutions from
+/// OCR_CONFUSIONS.
+///
+/// Every run of 15 recognized tokens is searched for readings with a valid checksum, preferring
+/// readings that need fewer substitutions. A reading is only returned as Confident if no other
+/// valid reading needs as few substitutions. Among invalid readings with the same number of
+/// substitutions, readings that a single word change would make valid rank ahead of the rest, so
+/// a phrase with one wrong word is found even when the text around it contains dictionary words.
+///
+/// Text longer than MAX_INPUT_LEN fails without diagnostics.
+pub fn from_ocr_text(text: &str) -> OcrParseResult {
+    if text.len() > MAX_INPUT_LEN {
+        return OcrParseResult::Failed(Vec::new());
+    }
+    let tokens = tokens(text);
+    let candidates: Vec<Vec<(usize, usize)>> = tokens.iter().map(|t| token_candidates(t)).collect();
+    let recognized: Vec<&Vec<(usize, usize)>> =
+        candidates.iter().filter(|c| !c.is_empty()).collect();
+    if recognized.len() < PHRASE_WORDS {
+        let diagnostics = tokens
+            .into_iter()
+            .zip(candidates.iter())
+            .map(|(token, candidates)| TokenDiagnostic {
+                token,
+                candidates: candidates
+                    .iter()
+                    .map(|&(i, cost)| (DICTIONARY[i].to_string(), cost))
+                    .collect(),
+            })
+            .collect();
+        return OcrParseResult::Failed(diagnostics);
+    }
+
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    let mut budget = MAX_TOTAL_READINGS;
+    for window in recognized.windows(PHRASE_WORDS) {
+        search_window(window, &mut budget, &mut valid, &mut invalid);
+        if budget == 0 {
+            break;
+        }
+    }
+    valid.sort_by_key(|r: &Reading| r.cost);
+    valid.dedup_by(|a, b| a.indices == b.indices);
+    if let Some(best) = valid.first() {
+        let unique = valid.len() == 1 || valid[1].cost > best.cost;
+        if unique {
+            let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+            entropy.copy_from_slice(&best.indices[..SEED_ENTROPY_WORDS]);
+            if let Ok(seed) = entropy_indices_to_seed(&entropy) {
+                return OcrParseResult::Confident {
+                    seed,
+                    phrase: best.phrase(),
+                    corrected: best.corrected.clone(),
+                };
+            }
+        }
+    }
+
+    // Looking for single-word repairs is expensive, so it is only done when the invalid readings
+    // will be returned.
+    keep_fewest_substitutions(&mut invalid);
+    if valid.len() < MAX_NEAR_MISSES {
+        for reading in invalid.iter_mut() {
+            reading.error_position = most_likely_error_position(&reading.phrase());
+        }
+        invalid.sort_by_key(|r: &Reading| (r.cost, r.error_position.is_none()));
+    }
+    let near_misses = valid
+        .iter()
+        .map(|r| r.candidate(true))
+        .chain(invalid.iter().map(|r| r.candidate(false)))
+        .take(MAX_NEAR_MISSES)
+        .collect();
+    OcrParseResult::NearMisses(near_misses)
+}
+
+/// Reading is one way of reading a window of 15 tokens.
+struct Reading {
+    indices: [usize; PHRASE_WORDS],
+    cost: usize,
+    corrected: Vec<usize>,
+    ambiguous: Vec<usize>,
+    error_position: Option<usize>,
+}
+
+impl Reading {
+    /// phrase returns the reading as a canonical phrase.
+    fn phrase(&self) -> String {
+        let words: Vec<&str> = self.indices.iter().map(|&i| DICTIONARY[i]).collect();
+        words.join(" ")
+    }
+
+    /// candidate converts the reading to an OcrCandidate.
+    fn candidate(&self, valid: bool) -> OcrCandidate {
+        let phrase = self.phrase();
+        let mut uncertain: Vec<usize> = self
+            .corrected
+            .iter()
+            .chain(self.ambiguous.iter())
+            .copied()
+            .collect();
+        uncertain.extend(self.error_position);
+        uncertain.sort_unstable();
+        uncertain.dedup();
+        OcrCandidate {
+            phrase,
+            substitutions: self.cost,
+            valid,
+            uncertain_positions: uncertain,
+        }
+    }
+}
+
+/// keep_fewest_substitutions reduces 'invalid' to the MAX_RANKED_MISSES readings with the fewest
+/// substitutions, keeping earlier readings on ties.
+fn keep_fewest_substitutions(invalid: &mut Vec<Reading>) {
+    invalid.sort_by_key(|r| r.cost);
+    invalid.truncate(MAX_RANKED_MISSES);
+}
+
+/// search_window checks the readings of a window of 15 tokens, taking one reading from 'budget'
+/// for each. Every valid reading is added to 'valid', and 'invalid' keeps the invalid readings
+/// with the fewest substitutions seen in any window so far.
+fn search_window(
+    window: &[&Vec<(usize, usize)>],
+    budget: &mut usize,
+    valid: &mut Vec<Reading>,
+    invalid: &mut Vec<Reading>,
+) {
+    let ambiguous: Vec<usize> = (0..PHRASE_WORDS).filter(|&i| window[i].len() > 1).collect();
+    let mut choice = [0usize; PHRASE_WORDS];
+    let mut checked = 0;
+    loop {
+        let mut indices = [0usize; PHRASE_WORDS];
+        let mut cost = 0;
+        let mut corrected = Vec::new();
+        for i in 0..PHRASE_WORDS {
+            let (index, c) = window[i][choice[i]];
+            indices[i] = index;
+            cost += c;
+            if c > 0 {
+                corrected.push(i);
+            }
+        }
+        let reading = Reading {
+            indices,
+            cost,
+            corrected,
+            ambiguous: ambiguous.clone(),
+            error_position: None,
+        };
+        if valid_indices(&indices) {
+            valid.push(reading);
+        } else {
+            invalid.push(reading);
+            if invalid.len() >= 2 * MAX_RANKED_MISSES {
+                keep_fewest_substitutions(invalid);
+            }
+        }
+
+        // Advance to the next combination of candidates, odometer style.
+        checked += 1;
+        *budget -= 1;
+        if checked == MAX_READINGS || *budget == 0 {
+            return;
+        }
+        let mut pos = 0;
+        loop {
+            if pos == PHRASE_WORDS {
+                return;
+            }
+            choice[pos] += 1;
+            if choice[pos] < window[pos].len() {
+                break;
+            }
+            choice[pos] = 0;
+            pos += 1;
+        }
+    }
+}
+
+/// tokens lowercases the text and splits it into runs of letters, digits, and '|', dropping
+/// tokens that are only digits or are too short to be a word.
+fn tokens(text: &str) -> Vec<String> {
+    text.to_ascii_lowercase()
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '|'))
+        .filter(|t| t.len() >= 3 && !t.bytes().all(|b| b.is_ascii_digit()))
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// token_index returns the dictionary index of a token that is a dictionary word or the 3 letter
+/// prefix of one.
+fn token_index(token: &str) -> Option<usize> {
+    match token.len() {
+        3 => index_of(token).map(|i| i as usize),
+        _ => dictionary_index(token),
+    }
+}
+
+/// dictionary_index returns the index of the word in the dictionary, ignoring case. Only exact
+/// matches are returned.
+fn dictionary_index(word: &str) -> Option<usize> {
+    // The longest word in the dictionary is 6 characters, lowercase into a stack buffer.
+    let mut buf = [0u8; 6];
+    if word.len() > buf.len() {
+        return None;
+    }
+    for (b, c) in buf.iter_mut().zip(word.bytes()) {
+        *b = c.to_ascii_lowercase();
+    }
+    let lower = &buf[..word.len()];
+    DICTIONARY
+        .binary_search_by(|w| w.as_bytes().cmp(lower))
+        .ok()
+}
+
+/// token_candidates returns the (dictionary index, substitutions) pairs that a token could be
+/// read as, sorted by the number of substitutions. A token that is already a word is only read as
+/// that word.
+fn token_candidates(token: &str) -> Vec<(usize, usize)> {
+    if let Some(index) = token_index(token) {
+        return vec![(index, 0)];
+    }
+    if token.len() > MAX_TOKEN_LEN {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<(usize, usize)> = Vec::new();
+    let mut seen = HashSet::new();
+    seen.insert(token.to_string());
+    let mut frontier = vec![token.to_string()];
+    for cost in 1..=MAX_TOKEN_SUBSTITUTIONS {
+        let mut next = Vec::new();
+        for s in &frontier {
+            for (from, to) in OCR_CONFUSIONS {
+                for (pos, _) in s.match_indices(from) {
+                    let rewritten = format!("{}{}{}", &s[..pos], to, &s[pos + from.len()..]);
+                    if seen.insert(rewritten.clone()) {
+                        next.push(rewritten);
+                    }
+                }
+            }
+        }
+        for s in &next {
+            if let Some(index) = token_index(s) {
+                if candidates.iter().all(|&(i, _)| i != index) {
+                    candidates.push((index, cost));
+                }
+            }
+        }
+        frontier = next;
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::{seed_phrase_to_seed, seed_to_seed_phrase};
+    use crate::random_seed;
+
+    // sheet lays a phrase out like a numbered backup sheet, applying 'corrupt' to each word.
+    fn sheet(phrase: &str, corrupt: impl Fn(usize, &str) -> String) -> String {
+        let mut text = "SEED BACKUP - keep this safe!\n".to_string();
+        for (i, word) in phrase.split(' ').enumerate() {
+            text += &format!("{}. {}\n", i + 1, corrupt(i, word).to_uppercase());
+        }
+        text + "page 1 of 1\n"
+    }
+
+    // ocr_corrupt applies the first matching confusion in reverse, the way OCR misreads text.
+    fn ocr_corrupt(word: &str) -> String {
+        for (read, written) in [("0", "o"), ("1", "l"), ("rn", "m"), ("5", "s"), ("8", "b")] {
+            if word.contains(written) {
+                return word.replacen(written, read, 1);
+            }
+        }
+        word.to_string()
+    }
+
+    #[test]
+    // Recover phrases from synthetic OCR corruptions of numbered backup sheets.
+    fn check_from_ocr_text() {
+        for _ in 0..20 {
+            let seed = random_seed();
+            let phrase = seed_to_seed_phrase(seed);
+
+            // A clean sheet needs no substitutions.
+            match from_ocr_text(&sheet(&phrase, |_, w| w.to_string())) {
+                OcrParseResult::Confident {
+                    seed: s,
+                    phrase: p,
+                    corrected,
+                } => {
+                    assert_eq!((s, p), (seed, phrase.clone()));
+                    assert!(corrected.is_empty());
+                }
+                other => panic!("clean sheet was not parsed: {:?}", other),
+            }
+
+            // Corrupt every other word.
+            let text = sheet(&phrase, |i, w| match i % 2 {
+                0 => ocr_corrupt(w),
+                _ => w.to_string(),
+            });
+            match from_ocr_text(&text) {
+                OcrParseResult::Confident {
+                    seed: s,
+                    phrase: p,
+                    corrected,
+                } => {
+                    assert_eq!((s, p), (seed, phrase.clone()));
+                    for i in corrected {
+                        assert_eq!(i % 2, 0);
+                    }
+                }
+                OcrParseResult::NearMisses(candidates) => {
+                    assert!(candidates[0].valid);
+                    assert!(candidates.iter().any(|c| c.phrase == phrase));
+                }
+                other => panic!("corrupted sheet was not parsed: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    // Check the substitution model on individual tokens.
+    fn check_token_candidates() {
+        let word = |i: usize| DICTIONARY[i];
+        let abbey = index_of("abbey").unwrap() as usize;
+        assert_eq!(word(abbey), "abbey");
+        assert_eq!(token_candidates("abbey"), vec![(abbey, 0)]);
+        assert_eq!(token_candidates("abb"), vec![(abbey, 0)]);
+        assert_eq!(token_candidates("a88ey"), vec![(abbey, 2)]);
+        assert!(token_candidates("qqqqq").is_empty());
+        assert!(token_candidates(&"0".repeat(40)).is_empty());
+        for (i, &w) in DICTIONARY.iter().enumerate() {
+            if w.contains('m') {
+                let read = w.replace('m', "rn");
+                assert!(token_candidates(&read).iter().any(|&(c, _)| c == i));
+            }
+        }
+        assert_eq!(
+            tokens("1. ABBEY\n12) a88ey, x |ow"),
+            vec!["abbey", "a88ey", "|ow"]
+        );
+    }
+
+    #[test]
+    // Unreadable text fails with diagnostics, and a wrong word produces near misses.
+    fn check_ocr_failures() {
+        // The header of 'sheet' contains dictionary words, so leave it out.
+        let phrase = seed_to_seed_phrase(random_seed());
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        words[3] = "qqqqq";
+        let text = words.join("\n");
+        match from_ocr_text(&text) {
+            OcrParseResult::Failed(diagnostics) => {
+                let bad: Vec<&TokenDiagnostic> = diagnostics
+                    .iter()
+                    .filter(|d| d.candidates.is_empty())
+                    .collect();
+                assert!(bad.iter().any(|d| d.token == "qqqqq"));
+                let words = diagnostics.iter().filter(|d| !d.candidates.is_empty());
+                assert!(words.count() >= PHRASE_WORDS - 1);
+            }
+            other => panic!("unreadable sheet was parsed: {:?}", other),
+        }
+        match from_ocr_text("") {
+            OcrParseResult::Failed(diagnostics) => assert!(diagnostics.is_empty()),
+            other => panic!("empty text was parsed: {:?}", other),
+        }
+
+        // Replace a word with a different dictionary word, which no substitution can fix.
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        let original = words[4];
+        words[4] = if original == DICTIONARY[0] {
+            DICTIONARY[1]
+        } else {
+            DICTIONARY[0]
+        };
+        let wrong = words.join(" ");
+        if seed_phrase_to_seed(&wrong).is_ok() {
+            return;
+        }
+        match from_ocr_text(&wrong) {
+            OcrParseResult::NearMisses(candidates) => {
+                assert_eq!(candidates[0].phrase, wrong);
+                assert!(!candidates[0].valid);
+                assert_eq!(candidates[0].uncertain_positions.len(), 1);
+            }
+            other => panic!("wrong word was not reported: {:?}", other),
+        }
+    }
+
+    #[test]
+    // A phrase with a wrong word is still a near miss when dictionary words surround it.
+    fn check_ocr_near_miss_with_noise() {
+        for _ in 0..2 {
+            let phrase = seed_to_seed_phrase(random_seed());
+            let mut words: Vec<&str> = phrase.split(' ').collect();
+            words[4] = if words[4] == DICTIONARY[0] {
+                DICTIONARY[1]
+            } else {
+                DICTIONARY[0]
+            };
+            let wrong = words.join(" ");
+            if seed_phrase_to_seed(&wrong).is_ok() {
+                continue;
+            }
+            let header: Vec<&str> = DICTIONARY[100..110].to_vec();
+            let text = format!("{}\n{}\n{}", header.join(" "), wrong, DICTIONARY[200]);
+            match from_ocr_text(&text) {
+                // A noise window can also happen to be one word away from a valid phrase, so the
+                // phrase is not necessarily the first near miss.
+                OcrParseResult::NearMisses(candidates) => {
+                    assert!(candidates.iter().any(|c| c.phrase == wrong && !c.valid));
+                }
+                other => panic!("wrong word was not reported: {:?}", other),
+            }
+        }
+
+        // Oversized text is rejected outright.
+        let text = format!("{} ", DICTIONARY[0]).repeat(MAX_INPUT_LEN);
+        assert_eq!(from_ocr_text(&text), OcrParseResult::Failed(Vec::new()));
+    }
+}
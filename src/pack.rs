@@ -0,0 +1,192 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! pack contains helpers for backing up several seeds with a single extended phrase.
+//!
+//! A packed phrase has three parts:
+//!
+//! - a count word, which is the dictionary word whose index is the number of seeds
+//! - the seeds, concatenated and split into 10 bit words; if the final word has fewer than 10
+//!   bits, they are its low bits and its high bits are zero, just like the 13th word of a standard
+//!   seed phrase
+//! - two checksum words, which are the first 20 bits of sha256("seed15 pack" || count || seeds)
+//!
+//! A packed phrase of n seeds has 3 + ceil(128n / 10) words, so a single seed packs into 16 words
+//! and the maximum of 8 seeds packs into 106 words. The 13 data words of a single packed seed are
+//! the entropy words of its seed phrase, but packed phrases are never 15 words long and can't be
+//! confused with a standard seed phrase.
+
+use anyhow::{bail, Error, Result};
+use dictionary_1024::DICTIONARY;
+use sha2::{Digest, Sha256};
+
+use crate::dictionary::lookup_index;
+use crate::phrase::checksum_indices_from_hash;
+use crate::Seed;
+
+/// MAX_PACKED_SEEDS is the largest number of seeds that can be packed into one phrase.
+pub const MAX_PACKED_SEEDS: usize = 8;
+
+/// PACK_SPECIFIER is the domain separator used for the checksum of a packed phrase.
+const PACK_SPECIFIER: &[u8] = b"seed15 pack";
+
+/// data_words returns the number of words needed to hold 'count' seeds.
+fn data_words(count: usize) -> usize {
+    (count * 128).div_ceil(10)
+}
+
+/// pack_checksum returns the indices of the checksum words for a set of seeds.
+fn pack_checksum(seeds: &[Seed]) -> (usize, usize) {
+    let mut hasher = Sha256::new();
+    hasher.update(PACK_SPECIFIER);
+    hasher.update([seeds.len() as u8]);
+    for seed in seeds {
+        hasher.update(seed);
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hasher.finalize());
+    checksum_indices_from_hash(&hash)
+}
+
+/// pack_seeds encodes between 1 and MAX_PACKED_SEEDS seeds into a single phrase. See the module
+/// documentation for the format.
+pub fn pack_seeds(seeds: &[Seed]) -> Result<String, Error> {
+    if seeds.is_empty() || seeds.len() > MAX_PACKED_SEEDS {
+        bail!(
+            "can pack between 1 and {} seeds, got {}",
+            MAX_PACKED_SEEDS,
+            seeds.len()
+        );
+    }
+
+    let mut words = vec![DICTIONARY[seeds.len()]];
+    let mut acc = 0usize;
+    let mut acc_bits = 0;
+    for byte in seeds.iter().flatten() {
+        acc = (acc << 8) | *byte as usize;
+        acc_bits += 8;
+        while acc_bits >= 10 {
+            acc_bits -= 10;
+            words.push(DICTIONARY[(acc >> acc_bits) & 0x3ff]);
+        }
+    }
+    if acc_bits > 0 {
+        words.push(DICTIONARY[acc & ((1 << acc_bits) - 1)]);
+    }
+
+    let (c1, c2) = pack_checksum(seeds);
+    words.push(DICTIONARY[c1]);
+    words.push(DICTIONARY[c2]);
+    Ok(words.join(" "))
+}
+
+/// unpack_seeds decodes a phrase produced by pack_seeds. Like seed_phrase_to_seed, only the
+/// dictionary prefix of each word is considered.
+pub fn unpack_seeds(phrase: &str) -> Result<Vec<Seed>, Error> {
+    let words: Vec<&str> = phrase.split(' ').collect();
+    let mut indices = Vec::with_capacity(words.len());
+    for (i, word) in words.iter().enumerate() {
+        if !word.is_ascii() {
+            bail!("word {} contains non-ascii characters", i + 1);
+        }
+        indices.push(lookup_index(word)?);
+    }
+
+    let count = indices[0];
+    if count == 0 || count > MAX_PACKED_SEEDS {
+        bail!("count word '{}' is not a valid seed count", words[0]);
+    }
+    let expected_words = 3 + data_words(count);
+    if words.len() != expected_words {
+        bail!(
+            "expecting {} words for {} seeds but got {} words",
+            expected_words,
+            count,
+            words.len()
+        );
+    }
+
+    // Unpack the data words into bytes. The final word may hold fewer than 10 bits, in which
+    // case its unused high bits must be zero.
+    let data = &indices[1..1 + data_words(count)];
+    let mut bytes = Vec::with_capacity(count * 16);
+    let mut acc = 0usize;
+    let mut acc_bits = 0;
+    for (i, &index) in data.iter().enumerate() {
+        let bits = if i == data.len() - 1 {
+            count * 128 - 10 * (data.len() - 1)
+        } else {
+            10
+        };
+        if index >= 1 << bits {
+            bail!(
+                "word {} is out of range for the final data word",
+                words[i + 1]
+            );
+        }
+        acc = (acc << bits) | index;
+        acc_bits += bits;
+        while acc_bits >= 8 {
+            acc_bits -= 8;
+            bytes.push((acc >> acc_bits) as u8);
+        }
+        acc &= (1 << acc_bits) - 1;
+    }
+    let seeds: Vec<Seed> = bytes
+        .chunks(16)
+        .map(|c| {
+            let mut seed: Seed = [0u8; 16];
+            seed.copy_from_slice(c);
+            seed
+        })
+        .collect();
+
+    let (c1, c2) = pack_checksum(&seeds);
+    if indices[expected_words - 2] != c1 || indices[expected_words - 1] != c2 {
+        bail!("packed phrase checksum is incorrect");
+    }
+    Ok(seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_to_seed_phrase;
+    use crate::random_seed;
+
+    #[test]
+    // Pack and unpack sets of seeds, and check that corrupted phrases are rejected.
+    fn check_pack_seeds() {
+        for count in [1, 2, 3, MAX_PACKED_SEEDS] {
+            let seeds: Vec<Seed> = (0..count).map(|_| random_seed()).collect();
+            let phrase = pack_seeds(&seeds).unwrap();
+            assert_eq!(phrase.split(' ').count(), 3 + data_words(count));
+            assert_eq!(unpack_seeds(&phrase).unwrap(), seeds);
+
+            // Changing any one word breaks the phrase.
+            let mut words: Vec<&str> = phrase.split(' ').collect();
+            for i in 0..words.len() {
+                let original = words[i];
+                words[i] = if original == "abbey" { "able" } else { "abbey" };
+                unpack_seeds(&words.join(" ")).unwrap_err();
+                words[i] = original;
+            }
+        }
+
+        // A single packed seed uses the same entropy words as its seed phrase.
+        let seed = random_seed();
+        let packed = pack_seeds(&[seed]).unwrap();
+        let phrase = seed_to_seed_phrase(seed);
+        let packed_words: Vec<&str> = packed.split(' ').collect();
+        let phrase_words: Vec<&str> = phrase.split(' ').collect();
+        assert_eq!(packed_words[1..14], phrase_words[..13]);
+
+        pack_seeds(&[]).unwrap_err();
+        pack_seeds(&[seed; MAX_PACKED_SEEDS + 1]).unwrap_err();
+        unpack_seeds("").unwrap_err();
+        unpack_seeds(&phrase).unwrap_err();
+        unpack_seeds(&format!("{} abbey", packed)).unwrap_err();
+    }
+}
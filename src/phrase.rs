@@ -8,11 +8,14 @@
 //!
 //! <https://blog.sia.tech/a-technical-breakdown-of-mysky-seeds-ba9964505978>
 
-use crate::Seed;
+use crate::derive::hash_fields;
+use crate::dictionary::{lookup_index, same_word, word_at};
+use crate::normalize::{InputError, MAX_INPUT_LEN};
+use crate::{random_seed, Seed};
 use anyhow::{bail, Error, Result};
-use dictionary_1024::{index_of_word, word_at_index, words_match};
+use dictionary_1024::{DICTIONARY, DICTIONARY_UNIQUE_PREFIX};
 use sha2::{Digest, Sha256};
-
+use std::collections::HashSet;
 
 /// SEED_ENTROPY_WORDS describes the number of words in a seed phrase that contribute to its
 /// fundamental entropy. These are the first 13 words.
@@ -24,6 +27,32 @@ pub const SEED_ENTROPY_WORDS: usize = 13;
 /// be corrected by brute-force with zero false positives.
 pub const SEED_CHECKSUM_WORDS: usize = 2;
 
+/// checksum_bits returns the number of bits of checksum in a seed phrase. Each of the
+/// SEED_CHECKSUM_WORDS checksum words carries 10 bits.
+pub fn checksum_bits() -> usize {
+    SEED_CHECKSUM_WORDS * 10
+}
+
+/// false_positive_probability returns the probability that a seed phrase with incorrect entropy
+/// words still passes the checksum, which is 2^-20, or roughly one in a million.
+pub fn false_positive_probability() -> f64 {
+    1.0 / (1u64 << checksum_bits()) as f64
+}
+
+/// ChecksumMatchMode controls how the checksum words of a seed phrase are compared against the
+/// checksum words computed from the seed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumMatchMode {
+    /// Prefix accepts any word that shares a dictionary prefix with the expected checksum word.
+    /// This is the standard behavior.
+    #[default]
+    Prefix,
+    /// ExactWord requires each checksum word to be exactly the canonical dictionary word. Phrases
+    /// that were transcribed using only word prefixes, or that use a different word with the same
+    /// prefix, will be rejected even though they are valid under the standard rules.
+    ExactWord,
+}
+
 /// seed_to_seed_phrase will convert a seed into a seed phrase.
 pub fn seed_to_seed_phrase(seed: Seed) -> String {
     // Add the entropy words. We process the seed one bit at a time.
@@ -58,7 +87,7 @@ pub fn seed_to_seed_phrase(seed: Seed) -> String {
         if i != 0 {
             phrase += " ";
         }
-        phrase += &word_at_index(word_index);
+        phrase += word_at(word_index).expect("word indices have at most 10 bits");
     }
 
     // Add the checksum words.
@@ -70,38 +99,313 @@ pub fn seed_to_seed_phrase(seed: Seed) -> String {
     phrase
 }
 
-/// seed_phrase_to_seed converts a seed phrase to a Uint8Array
+/// random_phrases lazily generates 'n' distinct valid seed phrases from fresh random seeds, for
+/// load tests and demos. Random seeds essentially never repeat, but the seeds are tracked so the
+/// phrases are guaranteed to be distinct, which costs 16 bytes of memory per phrase.
+pub fn random_phrases(n: usize) -> impl Iterator<Item = String> {
+    let mut seen = HashSet::new();
+    std::iter::repeat_with(random_seed)
+        .filter(move |seed| seen.insert(*seed))
+        .map(seed_to_seed_phrase)
+        .take(n)
+}
+
+/// seed_phrase_to_seed converts a seed phrase to a Uint8Array. Input longer than MAX_INPUT_LEN
+/// bytes is rejected with InputError::InputTooLarge before it is split into words.
 pub fn seed_phrase_to_seed(phrase: &str) -> Result<Seed, Error> {
-    // Break the phrase into its component words
-    let all_words: Vec<&str> = phrase.split(' ').collect();
-    let expected_words = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
-    if all_words.len() != expected_words {
+    parse_seed_phrase(phrase, false, ChecksumMatchMode::Prefix, "")
+}
+
+/// seed_phrase_to_seed_strict converts a seed phrase to a seed, additionally requiring that the
+/// 13th word is spelled exactly as the canonical dictionary word for its 8 bit value. The standard
+/// parser only looks at the prefix of each word, which means alternative spellings of the 13th word
+/// are accepted; strict mode rejects them.
+pub fn seed_phrase_to_seed_strict(phrase: &str) -> Result<Seed, Error> {
+    parse_seed_phrase(phrase, true, ChecksumMatchMode::Prefix, "")
+}
+
+/// seed_phrase_to_seed_with_checksum_mode converts a seed phrase to a seed, comparing the checksum
+/// words according to the provided mode. Using ChecksumMatchMode::ExactWord is stricter than the
+/// standard format, which means some phrases that other seed15 implementations accept will be
+/// rejected.
+pub fn seed_phrase_to_seed_with_checksum_mode(
+    phrase: &str,
+    mode: ChecksumMatchMode,
+) -> Result<Seed, Error> {
+    parse_seed_phrase(phrase, false, mode, "")
+}
+
+/// MAX_TAG_LEN is the longest checksum tag, in characters.
+pub const MAX_TAG_LEN: usize = 16;
+
+/// normalize_tag trims and lowercases a checksum tag, returning an error if the result is longer
+/// than MAX_TAG_LEN or contains anything other than ascii letters and digits.
+pub(crate) fn normalize_tag(tag: &str) -> Result<String, Error> {
+    let tag = tag.trim().to_ascii_lowercase();
+    if tag.len() > MAX_TAG_LEN {
+        bail!("tag can be at most {} characters", MAX_TAG_LEN);
+    }
+    if !tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        bail!("tag can only contain ascii letters and digits");
+    }
+    Ok(tag)
+}
+
+/// seed_to_seed_phrase_tagged converts a seed to a seed phrase whose checksum is computed over
+/// SHA-256(tag || seed) instead of SHA-256(seed). The entropy words are the same as those of the
+/// standard phrase, but the checksum words depend on the tag, so the phrase only validates with
+/// seed_phrase_to_seed_tagged and the same tag.
+///
+/// Tagged and untagged phrases are deliberately incompatible: a tagged phrase fails the standard
+/// validator, and other seed15 tools will report it as invalid. Anyone restoring a tagged phrase
+/// must know the tag. The tag is trimmed and lowercased, and must be at most MAX_TAG_LEN ascii
+/// letters and digits. The empty tag produces the standard format.
+pub fn seed_to_seed_phrase_tagged(seed: Seed, tag: &str) -> Result<String, Error> {
+    let tag = normalize_tag(tag)?;
+    let phrase = seed_to_seed_phrase(seed);
+    let mut words: Vec<&str> = phrase.split(' ').take(SEED_ENTROPY_WORDS).collect();
+    let (c1, c2) = checksum_word_indices_tagged(seed, &tag);
+    words.push(DICTIONARY[c1]);
+    words.push(DICTIONARY[c2]);
+    Ok(words.join(" "))
+}
+
+/// seed_phrase_to_seed_tagged converts a phrase produced by seed_to_seed_phrase_tagged back to a
+/// seed. The tag is normalized the same way, so "DV" and "dv" are the same tag.
+pub fn seed_phrase_to_seed_tagged(phrase: &str, tag: &str) -> Result<Seed, Error> {
+    let tag = normalize_tag(tag)?;
+    parse_seed_phrase(phrase, false, ChecksumMatchMode::Prefix, &tag)
+}
+
+/// seed_to_labeled_phrase converts a seed to a 16 word phrase that starts with a label word, so
+/// that users with several wallets can tell the phrases apart. The label is any dictionary word,
+/// matched by prefix like the other words and written in its canonical form. It is not covered by
+/// the checksum and carries no entropy; the remaining 15 words are the standard phrase.
+pub fn seed_to_labeled_phrase(seed: Seed, label_word: &str) -> Result<String, Error> {
+    if !label_word.is_ascii() {
+        bail!("label word contains non-ascii characters");
+    }
+    let label = DICTIONARY[lookup_index(label_word)?];
+    Ok(format!("{} {}", label, seed_to_seed_phrase(seed)))
+}
+
+/// labeled_phrase_to_seed converts a phrase produced by seed_to_labeled_phrase back to its seed
+/// and canonical label word. A labeled phrase has 16 words, so it is never mistaken for a
+/// standard phrase. A single seed packed with pack_seeds is also 16 words long, but the checksums
+/// differ, so each parser rejects the other format except with the usual one in a million chance.
+pub fn labeled_phrase_to_seed(phrase: &str) -> Result<(Seed, String), Error> {
+    let (label_word, rest) = match phrase.split_once(' ') {
+        Some(split) => split,
+        None => bail!("labeled phrase needs a label word followed by a seed phrase"),
+    };
+    if !label_word.is_ascii() {
+        bail!("label word contains non-ascii characters");
+    }
+    let label = DICTIONARY[lookup_index(label_word)?];
+    let seed = seed_phrase_to_seed(rest)?;
+    Ok((seed, label.to_string()))
+}
+
+/// parse_seed_phrase implements seed_phrase_to_seed. If 'strict' is set, the 13th word must be
+/// exactly equal to its canonical dictionary form. The checksum words are compared using 'mode',
+/// and are computed with the normalized 'tag'.
+fn parse_seed_phrase(
+    phrase: &str,
+    strict: bool,
+    mode: ChecksumMatchMode,
+    tag: &str,
+) -> Result<Seed, Error> {
+    let all_words = split_phrase(phrase, SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS)?;
+    let seed = entropy_words_to_seed(&all_words[..SEED_ENTROPY_WORDS], strict)?;
+
+    // Verify the checksum on the seed.
+    let checksum_words = checksum_words_from_indices(checksum_word_indices_tagged(seed, tag));
+    let checksum_match = |expected: &str, got: &str| match mode {
+        ChecksumMatchMode::Prefix => same_word(expected, got),
+        ChecksumMatchMode::ExactWord => expected == got,
+    };
+    if !checksum_match(&checksum_words[0], all_words[SEED_ENTROPY_WORDS]) {
+        bail!(
+            "first checksum word is incorrect, expecting prefix {} but got {}",
+            checksum_words[0],
+            all_words[SEED_ENTROPY_WORDS]
+        );
+    }
+    if !checksum_match(&checksum_words[1], all_words[SEED_ENTROPY_WORDS + 1]) {
+        bail!(
+            "second checksum word is incorrect, expecting prefix {} but got {}",
+            checksum_words[1],
+            all_words[SEED_ENTROPY_WORDS + 1]
+        );
+    }
+
+    // Success.
+    Ok(seed)
+}
+
+/// split_phrase splits a phrase into exactly 'expected_words' ascii words.
+fn split_phrase(phrase: &str, expected_words: usize) -> Result<Vec<&str>, Error> {
+    // Reject oversized input before looking at it, and count the words before collecting them so
+    // that the allocation doesn't grow with the input.
+    if phrase.len() > MAX_INPUT_LEN {
+        bail!(InputError::InputTooLarge { len: phrase.len() });
+    }
+    let word_count = phrase.split(' ').count();
+    if word_count != expected_words {
         bail!(
             "expecting {} words but got {} words",
             expected_words,
-            all_words.len()
+            word_count
+        );
+    }
+    let all_words: Vec<&str> = phrase.split(' ').collect();
+
+    // The dictionary lookups operate on bytes, reject anything that isn't ascii before it reaches
+    // them.
+    for (i, word) in all_words.iter().enumerate() {
+        if !word.is_ascii() {
+            bail!("word {} contains non-ascii characters", i + 1);
+        }
+    }
+    Ok(all_words)
+}
+
+/// entropy_words_to_seed looks up the 13 entropy words of a phrase and packs them into a seed. If
+/// 'strict' is set, the 13th word must be exactly equal to its canonical dictionary form.
+fn entropy_words_to_seed(words: &[&str], strict: bool) -> Result<Seed, Error> {
+    let mut indices = [0usize; SEED_ENTROPY_WORDS];
+    for i in 0..SEED_ENTROPY_WORDS {
+        let word_index = lookup_index(words[i])?;
+        if i == SEED_ENTROPY_WORDS - 1 {
+            if word_index > 255 {
+                bail!(
+                    "seed phrase is not valid: {} cannot be the 13th word prefix",
+                    &words[SEED_ENTROPY_WORDS - 1]
+                );
+            }
+            if strict && Some(words[i]) != word_at(word_index) {
+                bail!(
+                    "seed phrase is not valid: 13th word must be exactly {} but got {}",
+                    DICTIONARY[word_index],
+                    words[i]
+                );
+            }
+        }
+        indices[i] = word_index;
+    }
+    entropy_indices_to_seed(&indices)
+}
+
+/// MAX_CHECKSUM_WORDS is the largest number of checksum words supported by
+/// seed_to_seed_phrase_with_checksum_words.
+pub const MAX_CHECKSUM_WORDS: usize = 3;
+
+/// checksum_false_positive_rate returns the probability that a phrase with incorrect entropy words
+/// still passes a checksum of 'checksum_word_count' words. Each word carries 10 bits, so one word
+/// lets roughly one in a thousand transcription errors through, two words (the standard) one in a
+/// million, and three words one in a billion. Shorter checksums also make repair less reliable: a
+/// brute-force search over one or two wrong words finds many false candidates with a one word
+/// checksum.
+pub fn checksum_false_positive_rate(checksum_word_count: usize) -> f64 {
+    0.5f64.powi(10 * checksum_word_count as i32)
+}
+
+/// checksum_word_indices_n returns the indices of 'count' checksum words for a seed. Word i is
+/// bits 10i through 10i + 9 of SHA-256(seed), so the first two words are always the standard
+/// checksum words.
+fn checksum_word_indices_n(seed: Seed, count: usize) -> Vec<usize> {
+    let hash = Sha256::digest(seed);
+    (0..count)
+        .map(|i| {
+            (0..10).fold(0usize, |acc, j| {
+                let bit = 10 * i + j;
+                let set = (hash[bit / 8] >> (7 - bit % 8)) & 1;
+                (acc << 1) | set as usize
+            })
+        })
+        .collect()
+}
+
+/// check_checksum_word_count returns an error if a phrase can't have 'count' checksum words.
+fn check_checksum_word_count(count: usize) -> Result<(), Error> {
+    if count == 0 || count > MAX_CHECKSUM_WORDS {
+        bail!(
+            "checksum word count must be between 1 and {} but got {}",
+            MAX_CHECKSUM_WORDS,
+            count
         );
     }
+    Ok(())
+}
 
+/// seed_to_seed_phrase_with_checksum_words converts a seed to a phrase with 'checksum_word_count'
+/// checksum words instead of the standard two, trading robustness against transcription errors for
+/// a shorter phrase, or the other way around. See checksum_false_positive_rate for the trade-off.
+/// With two checksum words the result is the standard phrase; other counts produce phrases that
+/// the standard parser and other seed15 tools reject, so the count must be recorded along with the
+/// phrase. Between 1 and MAX_CHECKSUM_WORDS checksum words are supported.
+pub fn seed_to_seed_phrase_with_checksum_words(
+    seed: Seed,
+    checksum_word_count: usize,
+) -> Result<String, Error> {
+    check_checksum_word_count(checksum_word_count)?;
+    let phrase = seed_to_seed_phrase(seed);
+    let mut words: Vec<&str> = phrase.split(' ').take(SEED_ENTROPY_WORDS).collect();
+    for index in checksum_word_indices_n(seed, checksum_word_count) {
+        words.push(DICTIONARY[index]);
+    }
+    Ok(words.join(" "))
+}
+
+/// seed_phrase_to_seed_with_checksum_words parses a phrase produced by
+/// seed_to_seed_phrase_with_checksum_words with the same checksum word count. Checksum words are
+/// matched by prefix, like in the standard format.
+pub fn seed_phrase_to_seed_with_checksum_words(
+    phrase: &str,
+    checksum_word_count: usize,
+) -> Result<Seed, Error> {
+    check_checksum_word_count(checksum_word_count)?;
+    let all_words = split_phrase(phrase, SEED_ENTROPY_WORDS + checksum_word_count)?;
+    let seed = entropy_words_to_seed(&all_words[..SEED_ENTROPY_WORDS], false)?;
+    let expected = checksum_word_indices_n(seed, checksum_word_count);
+    for (i, &index) in expected.iter().enumerate() {
+        let got = all_words[SEED_ENTROPY_WORDS + i];
+        if !same_word(DICTIONARY[index], got) {
+            bail!(
+                "checksum word {} is incorrect, expecting prefix {} but got {}",
+                i + 1,
+                DICTIONARY[index],
+                got
+            );
+        }
+    }
+    Ok(seed)
+}
+
+/// entropy_indices_to_seed packs the dictionary indices of the 13 entropy words into a seed. An
+/// error is returned if any index is out of bounds for its position.
+pub(crate) fn entropy_indices_to_seed(
+    indices: &[usize; SEED_ENTROPY_WORDS],
+) -> Result<Seed, Error> {
     // Build the seed from the entropy words. We build the seed out one bit at a time. We convert
     // the word into a set of entropy bits, then iterate over the bits and add them to the seed.
     let mut seed: Seed = [0u8; 16];
     let mut current_byte = 0;
     let mut current_bit = 0;
-    for i in 0..SEED_ENTROPY_WORDS {
-        let word_index = index_of_word(all_words[i])?;
-
-        // Pack the bits into the seed.
+    for (i, &word_index) in indices.iter().enumerate() {
+        // All words have 10 bits except the final word, which has 8 bits.
         let mut bits = 10;
         if i == SEED_ENTROPY_WORDS - 1 {
             bits = 8;
-            if word_index > 255 {
-                bail!(
-                    "seed phrase is not valid: {} cannot be the 13th word prefix",
-                    &all_words[SEED_ENTROPY_WORDS - 1]
-                );
-            }
         }
+        if word_index >= 1 << bits {
+            bail!(
+                "word index {} is out of bounds for position {}",
+                word_index,
+                i
+            );
+        }
+
         for j in 0..bits {
             // Set the current bit if needed.
             let bit_is_set = (word_index & (1 << (bits - j - 1))) > 0;
@@ -117,37 +421,41 @@ pub fn seed_phrase_to_seed(phrase: &str) -> Result<Seed, Error> {
             }
         }
     }
-
-    // Verify the checksum on the seed.
-    let checksum_words = seed_to_checksum_words(seed);
-    if !words_match(&checksum_words[0], all_words[SEED_ENTROPY_WORDS]) {
-        bail!(
-            "first checksum word is incorrect, expecting prefix {} but got {}",
-            checksum_words[0],
-            all_words[SEED_ENTROPY_WORDS]
-        );
-    }
-    if !words_match(&checksum_words[1], all_words[SEED_ENTROPY_WORDS + 1]) {
-        bail!(
-            "second checksum word is incorrect, expecting prefix {} but got {}",
-            checksum_words[1],
-            all_words[SEED_ENTROPY_WORDS + 1]
-        );
-    }
-
-    // Success.
     Ok(seed)
 }
 
 /// seed_to_checksum_words will provide the checksum words for a given seed.
 fn seed_to_checksum_words(seed: Seed) -> [String; SEED_CHECKSUM_WORDS] {
+    checksum_words_from_indices(checksum_word_indices(seed))
+}
+
+/// checksum_words_from_indices returns the checksum words for a pair of checksum indices.
+fn checksum_words_from_indices((word1, word2): (usize, usize)) -> [String; SEED_CHECKSUM_WORDS] {
+    let word = |index| {
+        word_at(index)
+            .expect("checksum indices have 10 bits")
+            .to_string()
+    };
+    [word(word1), word(word2)]
+}
+
+/// checksum_word_indices returns the dictionary indices of the two checksum words for a seed. The
+/// first index is the first 10 bits of SHA-256(seed) and the second index is the next 10 bits, so
+/// other implementations can compare the checksum numerically instead of by word.
+pub fn checksum_word_indices(seed: Seed) -> (usize, usize) {
+    checksum_word_indices_tagged(seed, "")
+}
+
+/// checksum_word_indices_tagged returns the indices of the checksum words for a seed computed
+/// over SHA-256(tag || seed). The tag must already be normalized.
+pub(crate) fn checksum_word_indices_tagged(seed: Seed, tag: &str) -> (usize, usize) {
     // Hash the seed to get the checksum entropy.
-    let mut hasher = Sha256::new();
-    hasher.update(&seed);
-    let r = hasher.finalize();
-    let mut result = [0u8; 32];
-    result.copy_from_slice(&r);
+    checksum_indices_from_hash(&hash_fields(&[tag.as_bytes(), &seed]))
+}
 
+/// checksum_indices_from_hash converts the first 20 bits of the checksum hash into the indices of
+/// the two checksum words.
+pub(crate) fn checksum_indices_from_hash(result: &[u8; 32]) -> (usize, usize) {
     // Convert the first 20 bits of the entropy into two words.
     let mut word1: usize = (result[0] as usize) << 8;
     word1 += result[1] as usize;
@@ -156,7 +464,32 @@ fn seed_to_checksum_words(seed: Seed) -> [String; SEED_CHECKSUM_WORDS] {
     word2 &= 0xffff;
     word2 += (result[2] as usize) << 2;
     word2 >>= 6;
-    [word_at_index(word1), word_at_index(word2)]
+    (word1, word2)
+}
+
+/// ChecksumCache remembers the checksum words of the most recently checked seed, so that repeated
+/// checks of an unchanged seed (for example while a user edits a phrase) don't rehash the seed.
+#[derive(Clone, Debug, Default)]
+pub struct ChecksumCache {
+    seed: Option<Seed>,
+    words: [String; SEED_CHECKSUM_WORDS],
+}
+
+impl ChecksumCache {
+    /// new returns an empty ChecksumCache.
+    pub fn new() -> ChecksumCache {
+        ChecksumCache::default()
+    }
+
+    /// checksum_words_cached returns the checksum words for the seed, only recomputing them if
+    /// the seed differs from the previous call.
+    pub fn checksum_words_cached(&mut self, seed: Seed) -> &[String; SEED_CHECKSUM_WORDS] {
+        if self.seed != Some(seed) {
+            self.words = seed_to_checksum_words(seed);
+            self.seed = Some(seed);
+        }
+        &self.words
+    }
 }
 
 /// valid_seed_phrase will return an error if the seed phrase is not valid.
@@ -167,10 +500,252 @@ pub fn valid_seed_phrase(phrase: &str) -> Result<(), Error> {
     }
 }
 
+/// valid_seed_phrase_ct returns true if the seed phrase is valid, performing the same amount of
+/// work no matter which words are wrong. valid_seed_phrase stops at the first problem, so its
+/// running time reveals how many leading words were correct; servers that validate phrases on
+/// behalf of callers should use this function instead.
+///
+/// Every one of the first 15 words is looked up against the whole dictionary, the checksum is
+/// always computed, and all failures are combined with bitwise operations. The running time still
+/// depends on the length of the input and on the number of words, which are not secret.
+pub fn valid_seed_phrase_ct(phrase: &str) -> bool {
+    parse_seed_phrase_ct(phrase).1
+}
+
+/// seed_phrase_to_seed_ct converts a seed phrase to a seed with the same fixed amount of work as
+/// valid_seed_phrase_ct, and compares the checksum words with checksum_word_matches_ct. It is
+/// intended for code that validates phrases where an attacker can measure how long validation
+/// takes, such as a local process timing a wallet unlock. The error does not say which word was
+/// wrong, since that would undo the point of the constant time checks.
+pub fn seed_phrase_to_seed_ct(phrase: &str) -> Result<Seed, Error> {
+    match parse_seed_phrase_ct(phrase) {
+        (seed, true) => Ok(seed),
+        _ => bail!("seed phrase is not valid"),
+    }
+}
+
+/// checksum_word_matches_ct returns true if 'got' matches the dictionary word 'expected' by
+/// prefix, which is the same result as words_match. The checksum words are derived from the seed,
+/// so words_match can leak how much of a checksum word was correct through its early exits. This
+/// comparison always examines the full prefix of both words.
+pub fn checksum_word_matches_ct(expected: &str, got: &str) -> bool {
+    let prefix = |word: &str| {
+        let mut p = [0u8; DICTIONARY_UNIQUE_PREFIX];
+        for (p, b) in p.iter_mut().zip(word.as_bytes()) {
+            *p = *b;
+        }
+        p
+    };
+    let (e, g) = (prefix(expected), prefix(got));
+    let diff = e
+        .iter()
+        .zip(g.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    let long_enough =
+        (expected.len() >= DICTIONARY_UNIQUE_PREFIX) & (got.len() >= DICTIONARY_UNIQUE_PREFIX);
+    (diff == 0) & long_enough
+}
+
+/// parse_seed_phrase_ct implements valid_seed_phrase_ct and seed_phrase_to_seed_ct, returning
+/// the seed assembled from the entropy words and whether the phrase is valid. The seed is
+/// meaningless if the phrase is not valid.
+fn parse_seed_phrase_ct(phrase: &str) -> (Seed, bool) {
+    // The length of the input is not secret, so oversized input can be rejected up front, the
+    // same as in seed_phrase_to_seed.
+    if phrase.len() > MAX_INPUT_LEN {
+        return ([0u8; 16], false);
+    }
+    let mut words = phrase.split(' ');
+    let mut ok = true;
+    let mut all_words = [""; SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS];
+    let mut indices = [0usize; SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS];
+    for (index, all_word) in indices.iter_mut().zip(all_words.iter_mut()) {
+        // Missing words are treated as empty, which never matches.
+        *all_word = words.next().unwrap_or("");
+        let word = all_word.as_bytes();
+        let ascii = word.iter().fold(0u8, |acc, b| acc | (b & 0x80)) == 0;
+        let mut prefix = [0u8; DICTIONARY_UNIQUE_PREFIX];
+        for (p, b) in prefix.iter_mut().zip(word) {
+            *p = *b;
+        }
+
+        // Scan the whole dictionary rather than stopping at the match.
+        let mut found = 0usize;
+        for (i, dict_word) in DICTIONARY.iter().enumerate() {
+            let d = dict_word.as_bytes();
+            let diff = (d[0] ^ prefix[0]) | (d[1] ^ prefix[1]) | (d[2] ^ prefix[2]);
+            let matched = (diff == 0) as usize;
+            *index |= i * matched;
+            found |= matched;
+        }
+        ok &= (found == 1) & ascii & (word.len() >= DICTIONARY_UNIQUE_PREFIX);
+    }
+    ok &= words.next().is_none();
+    ok &= indices[SEED_ENTROPY_WORDS - 1] < 256;
+
+    // Mask the indices into range so that the seed can always be built and hashed.
+    let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+    for (e, index) in entropy.iter_mut().zip(indices.iter()) {
+        *e = index & 0x3ff;
+    }
+    entropy[SEED_ENTROPY_WORDS - 1] &= 0xff;
+    let seed = entropy_indices_to_seed(&entropy).expect("indices are masked into range");
+    let (c1, c2) = checksum_word_indices(seed);
+    ok &= checksum_word_matches_ct(DICTIONARY[c1], all_words[SEED_ENTROPY_WORDS]);
+    ok &= checksum_word_matches_ct(DICTIONARY[c2], all_words[SEED_ENTROPY_WORDS + 1]);
+    (seed, ok)
+}
+
+/// prevalidate performs the cheap checks on a seed phrase: it must have exactly 15 words separated
+/// by single spaces, and every word must be made of ASCII letters. It does not consult the
+/// dictionary or compute the checksum, so it can run on every keystroke to give fast feedback.
+/// Passing prevalidate does not mean the phrase is valid. The full parser only looks at the
+/// first three characters of each word, so it accepts some words that prevalidate rejects, such as
+/// "abbey1".
+pub fn prevalidate(phrase: &str) -> Result<(), Error> {
+    let words = phrase.split(' ').count();
+    if words != SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS {
+        bail!(
+            "expecting {} words but got {} words",
+            SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS,
+            words
+        );
+    }
+    for (i, word) in phrase.split(' ').enumerate() {
+        if word.is_empty() {
+            bail!("word {} is empty", i + 1);
+        }
+        if !word.bytes().all(|b| b.is_ascii_alphabetic()) {
+            bail!(
+                "word {} contains characters other than ascii letters",
+                i + 1
+            );
+        }
+    }
+    Ok(())
+}
+
+/// entropy_words_valid checks the 13 entropy words of a seed phrase without the checksum words,
+/// so that an incremental UI can validate them before the checksum words are entered. Every word
+/// must match a dictionary word by prefix, and the 13th word must be one of the first 256 words.
+pub fn entropy_words_valid(phrase_words: &[&str]) -> Result<(), Error> {
+    if phrase_words.len() != SEED_ENTROPY_WORDS {
+        bail!(
+            "expecting {} entropy words but got {} words",
+            SEED_ENTROPY_WORDS,
+            phrase_words.len()
+        );
+    }
+    for (i, word) in phrase_words.iter().enumerate() {
+        if !word.is_ascii() {
+            bail!("word {} contains non-ascii characters", i + 1);
+        }
+        let word_index = lookup_index(word)?;
+        if i == SEED_ENTROPY_WORDS - 1 && word_index > 255 {
+            bail!("{} cannot be the 13th word prefix", word);
+        }
+    }
+    Ok(())
+}
+
+/// parse_annotated converts a seed phrase copied from notes into a seed, ignoring annotations.
+/// The following are removed before the words are parsed:
+///
+/// - anything from a '#' to the end of its line
+/// - parenthesized text, e.g. "abbey (looks like 'abbot'?)", including nested parentheses
+///
+/// After removing annotations, words may be separated by any whitespace, including newlines.
+/// Unbalanced parentheses are an error, as is text longer than MAX_INPUT_LEN.
+pub fn parse_annotated(text: &str) -> Result<Seed, Error> {
+    if text.len() > MAX_INPUT_LEN {
+        bail!(InputError::InputTooLarge { len: text.len() });
+    }
+    let mut cleaned = String::with_capacity(text.len());
+    for line in text.lines() {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut depth = 0usize;
+        for c in line.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => bail!("annotation has an unmatched ')'"),
+                ')' => depth -= 1,
+                _ if depth == 0 => cleaned.push(c),
+                _ => {}
+            }
+        }
+        if depth != 0 {
+            bail!("annotation has an unmatched '('");
+        }
+        // Keep the words on separate lines apart.
+        cleaned.push(' ');
+    }
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    seed_phrase_to_seed(&words.join(" "))
+}
+
+/// seed_matches_phrase returns true if the phrase is a seed phrase for the seed. Rather than
+/// parsing the phrase, the seed is encoded and the phrases are compared word by word. The
+/// comparison ignores case and extra whitespace, and like seed_phrase_to_seed only considers the
+/// dictionary prefix of each word. Phrases longer than MAX_INPUT_LEN never match.
+pub fn seed_matches_phrase(seed: Seed, phrase: &str) -> bool {
+    if phrase.len() > MAX_INPUT_LEN {
+        return false;
+    }
+    let expected = seed_to_seed_phrase(seed);
+    let phrase = phrase.to_lowercase();
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    let expected_words: Vec<&str> = expected.split(' ').collect();
+    words.len() == expected_words.len()
+        && words
+            .iter()
+            .zip(expected_words.iter())
+            .all(|(a, b)| same_word(a, b))
+}
+
+/// repair_search_space returns the number of candidate phrases that a brute-force repair would
+/// need to evaluate if 'unknown_positions' of the entropy words are incorrect and it is not known
+/// which ones. Every combination of positions is tried, and each position can hold any of the 1024
+/// dictionary words except for the 13th word, which can only hold one of 256 words. The result
+/// saturates at u64::MAX.
+pub fn repair_search_space(unknown_positions: usize) -> u64 {
+    if unknown_positions > SEED_ENTROPY_WORDS {
+        return 0;
+    }
+
+    // Count the combinations that leave the 13th word alone, then the combinations that include
+    // the 13th word.
+    let full_positions = SEED_ENTROPY_WORDS as u64 - 1;
+    let k = unknown_positions as u64;
+    let without_last = binomial(full_positions, k).saturating_mul(1024u64.saturating_pow(k as u32));
+    if k == 0 {
+        return without_last;
+    }
+    let with_last = binomial(full_positions, k - 1)
+        .saturating_mul(1024u64.saturating_pow(k as u32 - 1))
+        .saturating_mul(256);
+    without_last.saturating_add(with_last)
+}
+
+/// binomial returns n choose k.
+fn binomial(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let mut result = 1;
+    for i in 0..k {
+        result = result * (n - i) / (i + 1);
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::random_seed;
+    use dictionary_1024::{index_of_word, word_at_index};
+    use userspace_rng::random256;
 
     // verify_conversion will convert a given seed into a phrase and then back into a seed,
     // confirming that the new seed has its original value.
@@ -190,6 +765,42 @@ mod tests {
         }
     }
 
+    // checksum_collision_rate generates random seeds, replaces one random entropy word in each
+    // phrase with a different word, and returns the fraction of corrupted phrases that still pass
+    // the checksum. With a 20 bit checksum the expected rate is about 2^-20.
+    fn checksum_collision_rate(samples: usize) -> f64 {
+        let mut collisions = 0;
+        for _ in 0..samples {
+            let phrase = seed_to_seed_phrase(random_seed());
+            let mut words: Vec<String> = phrase.split(' ').map(|w| w.to_string()).collect();
+
+            // Pick a position and a replacement word that is different from the original.
+            let r = random256();
+            let position = r[0] as usize % SEED_ENTROPY_WORDS;
+            let choices = if position == SEED_ENTROPY_WORDS - 1 {
+                256
+            } else {
+                1024
+            };
+            let original = index_of_word(&words[position]).unwrap();
+            let offset = 1 + (u16::from_le_bytes([r[1], r[2]]) as usize % (choices - 1));
+            words[position] = word_at_index((original + offset) % choices);
+
+            if valid_seed_phrase(&words.join(" ")).is_ok() {
+                collisions += 1;
+            }
+        }
+        collisions as f64 / samples as f64
+    }
+
+    #[test]
+    // Run a small sample of the checksum collision measurement. The expected rate is roughly one
+    // in a million, so any sample of this size should see at most a handful of collisions.
+    fn check_checksum_collision_rate() {
+        let rate = checksum_collision_rate(1000);
+        assert!(rate < 0.001, "collision rate {} is implausibly high", rate);
+    }
+
     #[test]
     // Verify that each of these bad seeds results in an error.
     fn check_unhappy_seeds() {
@@ -242,6 +853,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop, clippy::single_match)]
     // perform a basic test to see that a seed can be generated, converted into a seed phrase, and
     // then converted back.
     fn check_seed_phrases() {
@@ -294,4 +906,547 @@ mod tests {
             };
         }
     }
+
+    #[test]
+    // Check that strict mode rejects a non-canonical spelling of the 13th word while still
+    // accepting the canonical spelling.
+    fn check_strict_thirteenth_word() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        assert_eq!(seed_phrase_to_seed_strict(&phrase).unwrap(), seed);
+
+        // Append extra characters to the 13th word. The prefix still matches, so the lenient
+        // parser accepts it but the strict parser should not.
+        let mut words: Vec<String> = phrase.split(' ').map(|w| w.to_string()).collect();
+        words[12] += "xyz";
+        let altered_phrase = words.join(" ");
+        assert_eq!(seed_phrase_to_seed(&altered_phrase).unwrap(), seed);
+        seed_phrase_to_seed_strict(&altered_phrase).unwrap_err();
+    }
+
+    #[test]
+    // Check the repair search space against hand computed values.
+    fn check_repair_search_space() {
+        assert_eq!(repair_search_space(0), 1);
+        assert_eq!(repair_search_space(1), 12 * 1024 + 256);
+        assert_eq!(repair_search_space(2), 66 * 1024 * 1024 + 12 * 1024 * 256);
+        assert_eq!(repair_search_space(13), u64::MAX);
+        assert_eq!(repair_search_space(14), 0);
+    }
+
+    #[test]
+    // Check fixed patterns that exercise the maximum word indices and the 13th word boundary.
+    fn check_boundary_seeds() {
+        for byte in [0xffu8, 0xaa, 0x55] {
+            let seed = [byte; 16];
+            verify_conversion(seed);
+            let phrase = seed_to_seed_phrase(seed);
+            let words: Vec<&str> = phrase.split(' ').collect();
+            let word_index = index_of_word(words[SEED_ENTROPY_WORDS - 1]).unwrap();
+            assert!(word_index < 256);
+        }
+
+        // The all-ones seed should use the largest index for every entropy word.
+        let phrase = seed_to_seed_phrase([0xff; 16]);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        for word in &words[..SEED_ENTROPY_WORDS - 1] {
+            assert_eq!(index_of_word(word).unwrap(), 1023);
+        }
+        assert_eq!(index_of_word(words[SEED_ENTROPY_WORDS - 1]).unwrap(), 255);
+    }
+
+    #[test]
+    // Check that a checksum word sharing a prefix with the canonical word passes in prefix mode
+    // but fails in exact word mode.
+    fn check_checksum_match_mode() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        for mode in [ChecksumMatchMode::Prefix, ChecksumMatchMode::ExactWord] {
+            assert_eq!(
+                seed_phrase_to_seed_with_checksum_mode(&phrase, mode).unwrap(),
+                seed
+            );
+        }
+
+        let mut words: Vec<String> = phrase.split(' ').map(|w| w.to_string()).collect();
+        words[SEED_ENTROPY_WORDS + 1] = words[SEED_ENTROPY_WORDS + 1][..3].to_string() + "zz";
+        let altered_phrase = words.join(" ");
+        assert_eq!(
+            seed_phrase_to_seed_with_checksum_mode(&altered_phrase, ChecksumMatchMode::Prefix)
+                .unwrap(),
+            seed
+        );
+        seed_phrase_to_seed_with_checksum_mode(&altered_phrase, ChecksumMatchMode::ExactWord)
+            .unwrap_err();
+    }
+
+    #[test]
+    // Replay a corpus of malformed inputs, including inputs that used to panic, and check that
+    // each one is rejected with an error.
+    fn check_malformed_input_corpus() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let with_word = |i: usize, w: &str| {
+            let mut words = words.clone();
+            words[i] = w;
+            words.join(" ")
+        };
+
+        let corpus = vec![
+            "".to_string(),
+            " ".repeat(14),
+            "\0".repeat(1 << 16),
+            "abbey ".repeat(100_000),
+            // Multi-byte characters that straddle the dictionary prefix boundary.
+            with_word(0, "aa\u{e9}"),
+            with_word(12, "ab\u{1f600}"),
+            with_word(13, "ab\u{e9}"),
+            with_word(14, "\u{200b}abbey"),
+            with_word(3, "ab\0"),
+            with_word(5, ""),
+        ];
+        for input in corpus {
+            seed_phrase_to_seed(&input).unwrap_err();
+            seed_phrase_to_seed_strict(&input).unwrap_err();
+            assert!(!valid_seed_phrase_ct(&input));
+        }
+    }
+
+    #[test]
+    // The generated phrases are valid and distinct.
+    fn check_random_phrases() {
+        let phrases: Vec<String> = random_phrases(100).collect();
+        assert_eq!(phrases.len(), 100);
+        for phrase in &phrases {
+            seed_phrase_to_seed(phrase).unwrap();
+        }
+        let distinct: HashSet<&String> = phrases.iter().collect();
+        assert_eq!(distinct.len(), 100);
+        assert_eq!(random_phrases(0).count(), 0);
+    }
+
+    #[test]
+    // Phrases with 1, 2, and 3 checksum words round trip, and 2 words is the standard format.
+    fn check_checksum_word_count() {
+        for _ in 0..100 {
+            let seed = random_seed();
+            let standard = seed_to_seed_phrase(seed);
+            for count in 1..=MAX_CHECKSUM_WORDS {
+                let phrase = seed_to_seed_phrase_with_checksum_words(seed, count).unwrap();
+                let words: Vec<&str> = phrase.split(' ').collect();
+                assert_eq!(words.len(), SEED_ENTROPY_WORDS + count);
+                assert!(standard.starts_with(&words[..SEED_ENTROPY_WORDS + count.min(2)].join(" ")));
+                assert_eq!(
+                    seed_phrase_to_seed_with_checksum_words(&phrase, count).unwrap(),
+                    seed
+                );
+                if count != 2 {
+                    seed_phrase_to_seed(&phrase).unwrap_err();
+                }
+                let short: Vec<&str> = words.iter().map(|w| &w[..3]).collect();
+                assert_eq!(
+                    seed_phrase_to_seed_with_checksum_words(&short.join(" "), count).unwrap(),
+                    seed
+                );
+            }
+            assert_eq!(
+                seed_to_seed_phrase_with_checksum_words(seed, 2).unwrap(),
+                standard
+            );
+            let (c1, c2) = checksum_word_indices(seed);
+            assert_eq!(checksum_word_indices_n(seed, 2), vec![c1, c2]);
+
+            // A wrong final checksum word is caught for every count.
+            let phrase = seed_to_seed_phrase_with_checksum_words(seed, 3).unwrap();
+            let (rest, last) = phrase.rsplit_once(' ').unwrap();
+            let index = index_of_word(last).unwrap();
+            let wrong = format!("{} {}", rest, word_at_index((index + 1) % 1024));
+            seed_phrase_to_seed_with_checksum_words(&wrong, 3).unwrap_err();
+            seed_phrase_to_seed_with_checksum_words(&phrase, 2).unwrap_err();
+        }
+
+        let seed = random_seed();
+        seed_to_seed_phrase_with_checksum_words(seed, 0).unwrap_err();
+        seed_to_seed_phrase_with_checksum_words(seed, MAX_CHECKSUM_WORDS + 1).unwrap_err();
+        seed_phrase_to_seed_with_checksum_words(&seed_to_seed_phrase(seed), 0).unwrap_err();
+        assert_eq!(
+            checksum_false_positive_rate(2),
+            false_positive_probability()
+        );
+        assert_eq!(checksum_false_positive_rate(1), 1.0 / 1024.0);
+    }
+
+    #[test]
+    // Check the checksum indices of known seeds against independently computed hashes.
+    fn check_checksum_word_indices() {
+        // SHA-256 of 16 zero bytes starts with 0x374708.
+        assert_eq!(checksum_word_indices([0u8; 16]), (221, 112));
+        // SHA-256 of the bytes 0 through 15 starts with 0xbe45cb.
+        let mut seed = [0u8; 16];
+        for (i, b) in seed.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        assert_eq!(checksum_word_indices(seed), (761, 92));
+
+        // The indices are the ones used for the checksum words of the phrase.
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let (c1, c2) = checksum_word_indices(seed);
+        assert_eq!(words[SEED_ENTROPY_WORDS], word_at_index(c1));
+        assert_eq!(words[SEED_ENTROPY_WORDS + 1], word_at_index(c2));
+    }
+
+    #[test]
+    // Replay a corpus of known-bad inputs and check that each one fails for the expected reason,
+    // so that a change in which check rejects an input shows up as a failure.
+    fn check_invalid_input_errors() {
+        let phrase = seed_to_seed_phrase([7u8; 16]);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let with_word = |i: usize, w: &str| {
+            let mut words = words.clone();
+            words[i] = w;
+            words.join(" ")
+        };
+        let shifted = |i: usize| {
+            let index = index_of_word(words[i]).unwrap();
+            word_at_index((index + 1) % DICTIONARY.len())
+        };
+        let comma = format!("{},", words[0]);
+        let quoted = format!("\"{}", words[0]);
+
+        let corpus = vec![
+            ("".to_string(), "expecting 15 words but got 1 words"),
+            (words[0].to_string(), "expecting 15 words but got 1 words"),
+            (
+                vec![words[0]; 100].join(" "),
+                "expecting 15 words but got 100 words",
+            ),
+            (phrase.replace(' ', "\t"), "expecting 15 words but got 1"),
+            (
+                phrase.replace(' ', "\u{a0}"),
+                "expecting 15 words but got 1",
+            ),
+            (
+                phrase.replacen(' ', "  ", 1),
+                "expecting 15 words but got 16",
+            ),
+            (format!(" {}", phrase), "expecting 15 words but got 16"),
+            (format!("\u{feff}{}", phrase), "word 1 contains non-ascii"),
+            (with_word(4, "ab\u{301}c"), "word 5 contains non-ascii"),
+            (with_word(4, "\u{ff41}bbey"), "word 5 contains non-ascii"),
+            (with_word(2, "ab"), "at least 3 characters"),
+            (with_word(2, ""), "at least 3 characters"),
+            (with_word(0, &quoted), "was not found in dictionary"),
+            (with_word(0, "ab,"), "was not found in dictionary"),
+            (with_word(0, "zzz"), "was not found in dictionary"),
+            (with_word(6, "\0\0\0"), "was not found in dictionary"),
+            (with_word(12, DICTIONARY[1023]), "cannot be the 13th word"),
+            (
+                with_word(13, &shifted(13)),
+                "first checksum word is incorrect",
+            ),
+            (
+                with_word(14, &shifted(14)),
+                "second checksum word is incorrect",
+            ),
+        ];
+        for (input, expected) in corpus {
+            let err = seed_phrase_to_seed(&input).unwrap_err().to_string();
+            assert!(err.contains(expected), "{:?}: got {:?}", input, err);
+            seed_phrase_to_seed_strict(&input).unwrap_err();
+            seed_phrase_to_seed_ct(&input).unwrap_err();
+            assert!(!valid_seed_phrase_ct(&input));
+        }
+
+        // Only the unique prefix of a word is read, so trailing punctuation after it, including a
+        // trailing newline, is part of the standard format and is accepted.
+        for input in [with_word(0, &comma), format!("{}\n", phrase)] {
+            assert_eq!(seed_phrase_to_seed(&input).unwrap(), [7u8; 16]);
+        }
+    }
+
+    #[test]
+    // Check that the checksum cache returns the correct words as the seed changes.
+    fn check_checksum_cache() {
+        let mut cache = ChecksumCache::new();
+        let seed1 = random_seed();
+        let seed2 = random_seed();
+        assert_eq!(
+            cache.checksum_words_cached(seed1),
+            &seed_to_checksum_words(seed1)
+        );
+        assert_eq!(
+            cache.checksum_words_cached(seed1),
+            &seed_to_checksum_words(seed1)
+        );
+        assert_eq!(
+            cache.checksum_words_cached(seed2),
+            &seed_to_checksum_words(seed2)
+        );
+        assert_eq!(
+            cache.checksum_words_cached(seed1),
+            &seed_to_checksum_words(seed1)
+        );
+    }
+
+    #[test]
+    // Check seed_matches_phrase against matching, normalized, and non-matching phrases.
+    fn check_seed_matches_phrase() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        assert!(seed_matches_phrase(seed, &phrase));
+        let messy = format!("  {}  ", phrase.to_uppercase().replace(' ', "\t "));
+        assert!(seed_matches_phrase(seed, &messy));
+
+        // A different seed, a truncated phrase, and garbage should not match.
+        assert!(!seed_matches_phrase(random_seed(), &phrase));
+        let words: Vec<&str> = phrase.split(' ').collect();
+        assert!(!seed_matches_phrase(seed, &words[..14].join(" ")));
+        assert!(!seed_matches_phrase(seed, "aa\u{e9}"));
+
+        // Oversized input is rejected, even when it starts with the phrase.
+        let oversized = format!("{}{}", phrase, "a".repeat(MAX_INPUT_LEN));
+        assert!(!seed_matches_phrase(seed, &oversized));
+    }
+
+    #[test]
+    // Check that annotations and comments are removed before parsing.
+    fn check_parse_annotated() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+        let words: Vec<&str> = phrase.split(' ').collect();
+        let annotated = format!(
+            "# my backup\n{} (looks like 'abbey'?) {}\n{} # second line\n{} (copied (twice)) {}",
+            words[..4].join(" "),
+            words[4],
+            words[5..10].join(" "),
+            words[10..14].join(" "),
+            words[14]
+        );
+        assert_eq!(parse_annotated(&annotated).unwrap(), seed);
+        assert_eq!(parse_annotated(&phrase).unwrap(), seed);
+
+        // Unbalanced annotations are rejected.
+        parse_annotated(&format!("{} (oops", phrase)).unwrap_err();
+        parse_annotated(&format!("{} oops)", phrase)).unwrap_err();
+
+        // Oversized input is rejected, even when the excess is inside a comment.
+        let oversized = format!("{} # {}", phrase, "a".repeat(MAX_INPUT_LEN));
+        let err = parse_annotated(&oversized).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InputError>(),
+            Some(InputError::InputTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    // Check the reported checksum guarantees.
+    fn check_checksum_guarantees() {
+        assert_eq!(checksum_bits(), 20);
+        assert!((false_positive_probability() - 9.5367e-7).abs() < 1e-10);
+    }
+
+    #[test]
+    // Check the entropy words on their own.
+    fn check_entropy_words_valid() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        let words: Vec<&str> = phrase.split(' ').collect();
+        entropy_words_valid(&words[..SEED_ENTROPY_WORDS]).unwrap();
+        entropy_words_valid(&words).unwrap_err();
+        entropy_words_valid(&words[..SEED_ENTROPY_WORDS - 1]).unwrap_err();
+
+        // The 13th word must be one of the first 256 dictionary words.
+        let last = word_at_index(1023);
+        let mut bad = words[..SEED_ENTROPY_WORDS].to_vec();
+        bad[SEED_ENTROPY_WORDS - 1] = &last;
+        entropy_words_valid(&bad).unwrap_err();
+        bad[SEED_ENTROPY_WORDS - 1] = "abbey";
+        entropy_words_valid(&bad).unwrap();
+        bad[3] = "qqq";
+        entropy_words_valid(&bad).unwrap_err();
+        bad[3] = "caf\u{e9}";
+        entropy_words_valid(&bad).unwrap_err();
+    }
+
+    #[test]
+    // valid_seed_phrase_ct must agree with valid_seed_phrase on valid and invalid phrases.
+    fn check_valid_seed_phrase_ct() {
+        for _ in 0..100 {
+            let phrase = seed_to_seed_phrase(random_seed());
+            let words: Vec<&str> = phrase.split(' ').collect();
+            let last = word_at_index(1023);
+            let upper = words[2].to_uppercase();
+            let mut corpus = vec![
+                phrase.clone(),
+                words
+                    .iter()
+                    .map(|w| &w[..3])
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+                words[..14].join(" "),
+                format!("{} abbey", phrase),
+                format!("{} ", phrase),
+                format!(" {}", phrase),
+                phrase.replacen(' ', "  ", 1),
+            ];
+            for (i, w) in [
+                (0, "zzz"),
+                (4, "ab"),
+                (7, upper.as_str()),
+                (12, last.as_str()),
+                (13, "abbey"),
+                (14, "abbey"),
+                (14, "abb\u{e9}"),
+                (9, "a\0b"),
+            ] {
+                let mut corrupted = words.clone();
+                corrupted[i] = w;
+                corpus.push(corrupted.join(" "));
+            }
+            for input in corpus {
+                assert_eq!(
+                    valid_seed_phrase_ct(&input),
+                    valid_seed_phrase(&input).is_ok(),
+                    "{}",
+                    input
+                );
+            }
+        }
+
+        // Both reject a valid phrase followed by oversized trailing text.
+        let phrase = seed_to_seed_phrase(random_seed());
+        let oversized = format!("{}{}", phrase, "a".repeat(70_000));
+        assert!(valid_seed_phrase(&oversized).is_err());
+        assert!(!valid_seed_phrase_ct(&oversized));
+        seed_phrase_to_seed_ct(&oversized).unwrap_err();
+    }
+
+    #[test]
+    // seed_phrase_to_seed_ct and checksum_word_matches_ct must agree with the standard functions.
+    fn check_seed_phrase_to_seed_ct() {
+        for _ in 0..20 {
+            let seed = random_seed();
+            let phrase = seed_to_seed_phrase(seed);
+            assert_eq!(seed_phrase_to_seed_ct(&phrase).unwrap(), seed);
+            let words: Vec<&str> = phrase.split(' ').collect();
+            for i in [0, 12, 13, 14] {
+                let index = index_of_word(words[i]).unwrap();
+                for replacement in [
+                    word_at_index((index + 1) % 1024),
+                    words[i][..3].to_string(),
+                    words[i][..2].to_string(),
+                    format!("{}q", &words[i][..3]),
+                    "zzz".to_string(),
+                ] {
+                    let mut corrupted = words.clone();
+                    corrupted[i] = &replacement;
+                    let corrupted = corrupted.join(" ");
+                    assert_eq!(
+                        seed_phrase_to_seed_ct(&corrupted).ok(),
+                        seed_phrase_to_seed(&corrupted).ok(),
+                        "{}",
+                        corrupted
+                    );
+                }
+            }
+        }
+
+        // Compare every checksum word against a mix of candidate spellings.
+        for expected in DICTIONARY.iter().step_by(7) {
+            for got in [
+                "abbey",
+                "abb",
+                "ab",
+                "",
+                &expected[..3],
+                expected,
+                "caf\u{e9}",
+            ] {
+                let fast = same_word(expected, got);
+                assert_eq!(checksum_word_matches_ct(expected, got), fast);
+            }
+        }
+    }
+
+    #[test]
+    // prevalidate must reject bad shapes and accept every valid phrase.
+    fn check_prevalidate() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        prevalidate(&phrase).unwrap();
+        prevalidate(&phrase.to_uppercase()).unwrap();
+        let words: Vec<&str> = phrase.split(' ').collect();
+        prevalidate(&words[..14].join(" ")).unwrap_err();
+        prevalidate(&format!("{} {}", phrase, words[0])).unwrap_err();
+        prevalidate(&phrase.replacen(' ', "  ", 1)).unwrap_err();
+        prevalidate(&format!("{} ", &words[..14].join(" "))).unwrap_err();
+        prevalidate(&format!("{}1 {}", words[0], words[1..].join(" "))).unwrap_err();
+        prevalidate(&format!("caf\u{e9} {}", words[1..].join(" "))).unwrap_err();
+        prevalidate("").unwrap_err();
+    }
+
+    #[test]
+    // Tagged phrases round trip with their tag and fail with any other tag.
+    fn check_tagged_phrases() {
+        for _ in 0..20 {
+            let seed = random_seed();
+            let phrase = seed_to_seed_phrase_tagged(seed, "DV").unwrap();
+            assert_eq!(seed_phrase_to_seed_tagged(&phrase, "DV").unwrap(), seed);
+            assert_eq!(seed_phrase_to_seed_tagged(&phrase, " dv ").unwrap(), seed);
+            assert_eq!(seed_to_seed_phrase_tagged(seed, "dv").unwrap(), phrase);
+
+            // The entropy words are the standard ones, only the checksum words change. There is
+            // a one in a million chance that two tags share checksum words, so compare against
+            // several.
+            let standard = seed_to_seed_phrase(seed);
+            let entropy = |p: &str| -> String {
+                let words: Vec<&str> = p.split(' ').take(SEED_ENTROPY_WORDS).collect();
+                words.join(" ")
+            };
+            assert_eq!(entropy(&phrase), entropy(&standard));
+            let rejected = ["", "dw", "dv2", "vd"]
+                .iter()
+                .filter(|tag| seed_phrase_to_seed_tagged(&phrase, tag).is_err())
+                .count();
+            assert!(rejected >= 3);
+
+            // The empty tag is the standard format.
+            assert_eq!(seed_to_seed_phrase_tagged(seed, "").unwrap(), standard);
+            assert_eq!(seed_phrase_to_seed_tagged(&standard, " ").unwrap(), seed);
+        }
+
+        let seed = random_seed();
+        seed_to_seed_phrase_tagged(seed, "d v").unwrap_err();
+        seed_to_seed_phrase_tagged(seed, "caf\u{e9}").unwrap_err();
+        seed_to_seed_phrase_tagged(seed, &"a".repeat(MAX_TAG_LEN + 1)).unwrap_err();
+        seed_to_seed_phrase_tagged(seed, &"a".repeat(MAX_TAG_LEN)).unwrap();
+        seed_phrase_to_seed_tagged(&seed_to_seed_phrase(seed), "d-v").unwrap_err();
+    }
+
+    #[test]
+    // Labeled phrases round trip and are distinct from standard phrases.
+    fn check_labeled_phrase() {
+        let seed = random_seed();
+        let phrase = seed_to_labeled_phrase(seed, "abbey").unwrap();
+        assert_eq!(phrase.split(' ').count(), 16);
+        assert_eq!(
+            labeled_phrase_to_seed(&phrase).unwrap(),
+            (seed, "abbey".to_string())
+        );
+        assert_eq!(phrase, seed_to_labeled_phrase(seed, "abb").unwrap());
+        seed_phrase_to_seed(&phrase).unwrap_err();
+
+        // The label is matched by prefix and returned in canonical form.
+        let standard = seed_to_seed_phrase(seed);
+        let (_, label) = labeled_phrase_to_seed(&format!("abbx {}", standard)).unwrap();
+        assert_eq!(label, "abbey");
+
+        seed_to_labeled_phrase(seed, "zzz").unwrap_err();
+        seed_to_labeled_phrase(seed, "ab").unwrap_err();
+        seed_to_labeled_phrase(seed, "\u{e9}\u{e9}\u{e9}").unwrap_err();
+        labeled_phrase_to_seed(&standard).unwrap_err();
+        labeled_phrase_to_seed(&format!("zzz {}", standard)).unwrap_err();
+        labeled_phrase_to_seed("abbey").unwrap_err();
+        labeled_phrase_to_seed("").unwrap_err();
+    }
 }
@@ -0,0 +1,239 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! qr contains helpers for transferring a payload that is too large for a single QR code as a
+//! sequence of QR codes, for example as an animated QR code. Rendering and scanning the codes is
+//! left to the application; this module only produces and reassembles the chunks.
+//!
+//! Every chunk is self-describing:
+//!
+//! | bytes | field                                                   |
+//! |-------|---------------------------------------------------------|
+//! | 0..4  | the magic bytes "S15Q"                                  |
+//! | 4..6  | the index of the chunk, big endian                      |
+//! | 6..8  | the total number of chunks, big endian                  |
+//! | 8..12 | CRC32 of bytes 4..8 followed by the payload, big endian |
+//! | 12..  | the payload                                             |
+
+use anyhow::{bail, Error, Result};
+
+/// QR_CHUNK_MAGIC identifies a chunk of a QR sequence.
+const QR_CHUNK_MAGIC: &[u8; 4] = b"S15Q";
+
+/// QR_CHUNK_HEADER_LEN is the number of bytes in a chunk before the payload.
+pub const QR_CHUNK_HEADER_LEN: usize = 12;
+
+/// crc32 computes the IEEE CRC32 of the provided byte slices, as if they were concatenated.
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in parts.iter().flat_map(|p| p.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// qr_sequence_encode splits data into chunks that each carry at most 'max_chunk' bytes of
+/// payload, plus a QR_CHUNK_HEADER_LEN byte header. Empty data produces a single empty chunk.
+///
+/// Panics if 'max_chunk' is zero or if the data needs more than 65535 chunks.
+pub fn qr_sequence_encode(data: &[u8], max_chunk: usize) -> Vec<Vec<u8>> {
+    assert!(max_chunk > 0, "max_chunk must be greater than zero");
+    let total = data.len().div_ceil(max_chunk).max(1);
+    assert!(
+        total <= u16::MAX as usize,
+        "data needs more than 65535 chunks"
+    );
+
+    let mut chunks = Vec::with_capacity(total);
+    for index in 0..total {
+        let start = index * max_chunk;
+        let payload = &data[start..data.len().min(start + max_chunk)];
+        let mut position = [0u8; 4];
+        position[..2].copy_from_slice(&(index as u16).to_be_bytes());
+        position[2..].copy_from_slice(&(total as u16).to_be_bytes());
+
+        let mut chunk = Vec::with_capacity(QR_CHUNK_HEADER_LEN + payload.len());
+        chunk.extend_from_slice(QR_CHUNK_MAGIC);
+        chunk.extend_from_slice(&position);
+        chunk.extend_from_slice(&crc32(&[&position, payload]).to_be_bytes());
+        chunk.extend_from_slice(payload);
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// DecodeProgress describes the state of a QrSequenceDecoder after a chunk is pushed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeProgress {
+    /// Incomplete lists the indices of the chunks that are still missing.
+    Incomplete {
+        /// missing holds the missing chunk indices in ascending order.
+        missing: Vec<usize>,
+    },
+    /// Duplicate indicates that the chunk at this index had already been received. The chunk was
+    /// identical to the one received before, so nothing changed.
+    Duplicate {
+        /// index is the index of the duplicate chunk.
+        index: usize,
+    },
+    /// Complete holds the reassembled payload.
+    Complete(Vec<u8>),
+}
+
+/// QrSequenceDecoder reassembles the chunks produced by qr_sequence_encode, which may arrive in
+/// any order.
+#[derive(Clone, Debug, Default)]
+pub struct QrSequenceDecoder {
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl QrSequenceDecoder {
+    /// new creates a decoder that has not received any chunks.
+    pub fn new() -> QrSequenceDecoder {
+        QrSequenceDecoder::default()
+    }
+
+    /// push_chunk adds a scanned chunk. An error is returned if the chunk is malformed, fails its
+    /// CRC, disagrees with earlier chunks about the total number of chunks, or has the same index
+    /// as an earlier chunk but different contents. A rejected chunk does not change the decoder.
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<DecodeProgress, Error> {
+        if chunk.len() < QR_CHUNK_HEADER_LEN {
+            bail!(
+                "chunk is only {} bytes, too short for a header",
+                chunk.len()
+            );
+        }
+        if &chunk[..4] != QR_CHUNK_MAGIC {
+            bail!("chunk does not start with the QR sequence magic bytes");
+        }
+        let index = u16::from_be_bytes([chunk[4], chunk[5]]) as usize;
+        let total = u16::from_be_bytes([chunk[6], chunk[7]]) as usize;
+        let crc = u32::from_be_bytes([chunk[8], chunk[9], chunk[10], chunk[11]]);
+        let payload = &chunk[QR_CHUNK_HEADER_LEN..];
+        if crc32(&[&chunk[4..8], payload]) != crc {
+            bail!("chunk CRC does not match, the chunk is corrupted");
+        }
+        if index >= total {
+            bail!("chunk index {} is out of range for {} chunks", index, total);
+        }
+        if self.chunks.is_empty() {
+            self.chunks = vec![None; total];
+        } else if self.chunks.len() != total {
+            bail!(
+                "chunk claims {} chunks but earlier chunks claimed {}",
+                total,
+                self.chunks.len()
+            );
+        }
+
+        match &self.chunks[index] {
+            Some(existing) if existing.as_slice() == payload => {
+                return Ok(DecodeProgress::Duplicate { index });
+            }
+            Some(_) => bail!(
+                "chunk {} differs from the chunk {} received earlier",
+                index,
+                index
+            ),
+            None => self.chunks[index] = Some(payload.to_vec()),
+        }
+
+        let missing: Vec<usize> = (0..total).filter(|&i| self.chunks[i].is_none()).collect();
+        if !missing.is_empty() {
+            return Ok(DecodeProgress::Incomplete { missing });
+        }
+        let data = self.chunks.iter().flatten().flatten().copied().collect();
+        Ok(DecodeProgress::Complete(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // Check the CRC against the standard check value.
+    fn check_crc32() {
+        assert_eq!(crc32(&[b"123456789"]), 0xcbf4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xcbf4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    // Reassemble chunks out of order, with duplicates, corruption, and mismatched totals.
+    fn check_qr_sequence() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        let chunks = qr_sequence_encode(&data, 300);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].len(), QR_CHUNK_HEADER_LEN + 100);
+
+        // Out of order assembly, with a duplicate along the way.
+        let mut decoder = QrSequenceDecoder::new();
+        assert_eq!(
+            decoder.push_chunk(&chunks[2]).unwrap(),
+            DecodeProgress::Incomplete {
+                missing: vec![0, 1, 3]
+            }
+        );
+        assert_eq!(
+            decoder.push_chunk(&chunks[0]).unwrap(),
+            DecodeProgress::Incomplete {
+                missing: vec![1, 3]
+            }
+        );
+        assert_eq!(
+            decoder.push_chunk(&chunks[2]).unwrap(),
+            DecodeProgress::Duplicate { index: 2 }
+        );
+
+        // A corrupted chunk is rejected without changing the decoder.
+        let mut corrupted = chunks[3].clone();
+        corrupted[QR_CHUNK_HEADER_LEN + 5] ^= 1;
+        decoder.push_chunk(&corrupted).unwrap_err();
+        let mut corrupted = chunks[3].clone();
+        corrupted[5] = 1;
+        decoder.push_chunk(&corrupted).unwrap_err();
+        decoder.push_chunk(&chunks[3][..8]).unwrap_err();
+        decoder.push_chunk(b"not a chunk at all").unwrap_err();
+
+        // A chunk from a sequence with a different total is rejected.
+        let other = qr_sequence_encode(&data, 100);
+        decoder.push_chunk(&other[1]).unwrap_err();
+
+        assert_eq!(
+            decoder.push_chunk(&chunks[3]).unwrap(),
+            DecodeProgress::Incomplete { missing: vec![1] }
+        );
+        assert_eq!(
+            decoder.push_chunk(&chunks[1]).unwrap(),
+            DecodeProgress::Complete(data.clone())
+        );
+
+        // A chunk with the same index and total but different contents conflicts.
+        let mut altered = data.clone();
+        altered[0] ^= 1;
+        let altered = qr_sequence_encode(&altered, 300);
+        decoder.push_chunk(&altered[0]).unwrap_err();
+
+        // Empty data and data that fits in a single chunk.
+        let mut decoder = QrSequenceDecoder::new();
+        let chunks = qr_sequence_encode(&[], 300);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(
+            decoder.push_chunk(&chunks[0]).unwrap(),
+            DecodeProgress::Complete(Vec::new())
+        );
+        let mut decoder = QrSequenceDecoder::new();
+        let chunks = qr_sequence_encode(&data, 1000);
+        assert_eq!(
+            decoder.push_chunk(&chunks[0]).unwrap(),
+            DecodeProgress::Complete(data)
+        );
+    }
+}
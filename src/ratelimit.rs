@@ -0,0 +1,269 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! ratelimit contains a rate limited wrapper around seed phrase validation for servers that check
+//! phrases on behalf of callers. Without a limit, an endpoint that validates phrases can be used
+//! to brute-force phrases that pass the checksum. The limiter is deliberately simple: state is
+//! kept in memory only and is lost on restart. It is only available with the 'ratelimit' feature.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Error, Result};
+
+use crate::phrase::{checksum_word_indices, valid_seed_phrase};
+
+/// MAX_SHAPED_INPUT is the longest input that receives full validation work even when it is
+/// obviously invalid. The longest possible canonical phrase is 104 bytes, so anything longer than
+/// this is rejected immediately.
+const MAX_SHAPED_INPUT: usize = 256;
+
+/// MIN_PRUNE_LEN is the number of buckets at which the validator starts pruning buckets that have
+/// refilled completely.
+const MIN_PRUNE_LEN: usize = 1024;
+
+/// Throttled is the error returned when a caller has exceeded its rate limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Throttled;
+
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "too many validation attempts, try again later")
+    }
+}
+
+impl std::error::Error for Throttled {}
+
+/// ThrottlePolicy describes a token bucket. Each caller starts with 'burst' attempts and regains
+/// one attempt every 'refill_interval', up to a maximum of 'burst'.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThrottlePolicy {
+    /// burst is the number of attempts a caller can make back to back.
+    pub burst: u32,
+    /// refill_interval is the time it takes to regain a single attempt.
+    pub refill_interval: Duration,
+}
+
+/// Bucket is the token bucket for a single caller.
+struct Bucket {
+    tokens: u32,
+    last_refill: Instant,
+}
+
+/// ThrottledValidator validates seed phrases while limiting how often each caller can do so.
+/// Callers are identified by a key supplied by the application, such as an account id. One bucket
+/// is kept per key until it has refilled completely, at which point it is indistinguishable from a
+/// new bucket and may be dropped, so memory use is bounded by the number of callers that were
+/// active within the last 'burst' refill intervals.
+pub struct ThrottledValidator {
+    policy: ThrottlePolicy,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    attempts: AtomicU64,
+    denials: AtomicU64,
+}
+
+impl ThrottledValidator {
+    /// new creates a validator that enforces the given policy.
+    pub fn new(policy: ThrottlePolicy) -> ThrottledValidator {
+        ThrottledValidator {
+            policy,
+            buckets: Mutex::new(HashMap::new()),
+            attempts: AtomicU64::new(0),
+            denials: AtomicU64::new(0),
+        }
+    }
+
+    /// validate checks the phrase on behalf of the caller identified by 'key'. If the caller is
+    /// out of attempts, an error wrapping Throttled is returned without looking at the phrase;
+    /// callers can detect this with `err.is::<Throttled>()`. Otherwise the result is the same as
+    /// valid_seed_phrase.
+    ///
+    /// Invalid phrases that fail early, for example because they have the wrong number of words,
+    /// still perform the checksum work so that the response time says little about why a phrase
+    /// was rejected. Inputs longer than 256 bytes are rejected without the extra work.
+    pub fn validate(&self, key: &str, phrase: &str) -> Result<(), Error> {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        if !self.take_token(key, Instant::now()) {
+            self.denials.fetch_add(1, Ordering::Relaxed);
+            return Err(Error::new(Throttled));
+        }
+
+        let result = valid_seed_phrase(phrase);
+        if result.is_err() && phrase.len() <= MAX_SHAPED_INPUT {
+            black_box(checksum_word_indices(black_box([0u8; 16])));
+        }
+        result
+    }
+
+    /// attempts returns the total number of calls to validate, including denied calls.
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// denials returns the number of calls to validate that were denied by the rate limit.
+    pub fn denials(&self) -> u64 {
+        self.denials.load(Ordering::Relaxed)
+    }
+
+    /// refills returns the number of whole refill intervals that have passed since the bucket was
+    /// last refilled, capped at the burst size.
+    fn refills(&self, bucket: &Bucket, now: Instant) -> u32 {
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        let interval = self.policy.refill_interval.as_nanos().max(1);
+        (elapsed.as_nanos() / interval).min(self.policy.burst as u128) as u32
+    }
+
+    /// take_token refills the caller's bucket as of 'now' and then takes one token from it,
+    /// returning false if the bucket is empty.
+    fn take_token(&self, key: &str, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // Before adding a bucket, drop the buckets that have refilled completely. Pruning only
+        // when the map reaches a power of two keeps the cost per call constant on average.
+        let len = buckets.len();
+        if len >= MIN_PRUNE_LEN && len.is_power_of_two() && !buckets.contains_key(key) {
+            let burst = self.policy.burst;
+            buckets.retain(|_, b| b.tokens.saturating_add(self.refills(b, now)) < burst);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.policy.burst,
+            last_refill: now,
+        });
+
+        // Only whole intervals are credited, the remainder carries over to the next call.
+        let refills = self.refills(bucket, now);
+        if refills > 0 {
+            bucket.tokens = bucket.tokens.saturating_add(refills).min(self.policy.burst);
+            bucket.last_refill = if bucket.tokens == self.policy.burst {
+                now
+            } else {
+                bucket.last_refill + self.policy.refill_interval * refills
+            };
+        }
+
+        if bucket.tokens == 0 {
+            return false;
+        }
+        bucket.tokens -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_to_seed_phrase;
+    use crate::random_seed;
+
+    #[test]
+    // Exceed the policy with a burst and check for denials.
+    fn check_throttled_validator() {
+        let policy = ThrottlePolicy {
+            burst: 3,
+            refill_interval: Duration::from_secs(3600),
+        };
+        let validator = ThrottledValidator::new(policy);
+        let phrase = seed_to_seed_phrase(random_seed());
+
+        // The first attempts in a burst are answered normally.
+        validator.validate("alice", &phrase).unwrap();
+        validator
+            .validate("alice", "not a seed phrase")
+            .unwrap_err();
+        validator.validate("alice", &phrase).unwrap();
+        let err = validator.validate("alice", &phrase).unwrap_err();
+        assert!(err.is::<Throttled>());
+        let err = validator
+            .validate("alice", "not a seed phrase")
+            .unwrap_err();
+        assert!(err.is::<Throttled>());
+
+        // Other callers are not affected.
+        validator.validate("bob", &phrase).unwrap();
+        assert_eq!(validator.attempts(), 6);
+        assert_eq!(validator.denials(), 2);
+    }
+
+    #[test]
+    // Check that attempts are restored one interval at a time, up to the burst size.
+    fn check_token_refill() {
+        let interval = Duration::from_millis(50);
+        let validator = ThrottledValidator::new(ThrottlePolicy {
+            burst: 3,
+            refill_interval: interval,
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(validator.take_token("alice", start));
+        }
+        assert!(!validator.take_token("alice", start));
+
+        // A single interval restores a single attempt, and partial intervals carry over.
+        let t = start + interval + interval / 5;
+        assert!(validator.take_token("alice", t));
+        assert!(!validator.take_token("alice", t));
+        assert!(validator.take_token("alice", start + interval * 2));
+        assert!(!validator.take_token("alice", start + interval * 2));
+
+        // A long wait restores the full burst, but no more.
+        let t = start + interval * 100;
+        for _ in 0..3 {
+            assert!(validator.take_token("alice", t));
+        }
+        assert!(!validator.take_token("alice", t));
+
+        // A burst close to u32::MAX does not overflow when refilling.
+        let validator = ThrottledValidator::new(ThrottlePolicy {
+            burst: u32::MAX,
+            refill_interval: interval,
+        });
+        assert!(validator.take_token("alice", start));
+        assert!(validator.take_token("alice", start + interval * 10));
+    }
+
+    #[test]
+    // Check that buckets which have refilled completely are pruned and the others are kept.
+    fn check_bucket_pruning() {
+        let interval = Duration::from_secs(1);
+        let validator = ThrottledValidator::new(ThrottlePolicy {
+            burst: 2,
+            refill_interval: interval,
+        });
+        let start = Instant::now();
+        assert!(validator.take_token("alice", start));
+        assert!(validator.take_token("alice", start));
+        for i in 0..MIN_PRUNE_LEN * 2 {
+            assert!(validator.take_token(&format!("caller {}", i), start));
+        }
+        assert_eq!(
+            validator.buckets.lock().unwrap().len(),
+            MIN_PRUNE_LEN * 2 + 1
+        );
+
+        // Before a full interval has passed no caller has refilled, so nothing is pruned.
+        let t = start + interval / 2;
+        for i in 0..MIN_PRUNE_LEN * 2 {
+            assert!(validator.take_token(&format!("late caller {}", i), t));
+        }
+        assert!(validator.buckets.lock().unwrap().len() > MIN_PRUNE_LEN * 4);
+
+        // Once everyone has refilled, new callers trigger pruning.
+        let t = start + interval * 4;
+        for i in 0..MIN_PRUNE_LEN * 4 {
+            assert!(validator.take_token(&format!("new caller {}", i), t));
+        }
+        assert!(validator.buckets.lock().unwrap().len() <= MIN_PRUNE_LEN * 4);
+
+        // A pruned caller starts over with a full burst.
+        assert!(validator.take_token("alice", t));
+        assert!(validator.take_token("alice", t));
+        assert!(!validator.take_token("alice", t));
+    }
+}
@@ -0,0 +1,504 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! repair contains helpers for finding and explaining corrections to seed phrases that were
+//! transcribed incorrectly.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use anyhow::{Error, Result};
+use dictionary_1024::DICTIONARY;
+
+use crate::dictionary::{index_of, same_word};
+use crate::phrase::{
+    checksum_word_indices_tagged, entropy_indices_to_seed, normalize_tag, repair_search_space,
+    valid_seed_phrase, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS,
+};
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// ESTIMATE_SAMPLES is the number of candidate evaluations timed by estimate_repair_time.
+const ESTIMATE_SAMPLES: u32 = 4096;
+
+/// RepairMode selects how many incorrect words a repair searches for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepairMode {
+    /// OneWord searches every phrase that differs in one word.
+    OneWord,
+    /// TwoWord searches every phrase that differs in one word, and then every phrase that
+    /// differs in two words, like repair_seed_phrase_deadline.
+    TwoWord,
+}
+
+/// repair_changes compares a phrase to its repaired form and returns a (position, old_word,
+/// new_word) tuple for every word that differs, so that a UI can confirm each correction with the
+/// user. Words are split on any whitespace and compared exactly. If one phrase has more words
+/// than the other, the missing words are reported as empty strings.
+pub fn repair_changes(original: &str, repaired: &str) -> Vec<(usize, String, String)> {
+    let original: Vec<&str> = original.split_whitespace().collect();
+    let repaired: Vec<&str> = repaired.split_whitespace().collect();
+    let mut changes = Vec::new();
+    for i in 0..original.len().max(repaired.len()) {
+        let old = original.get(i).copied().unwrap_or("");
+        let new = repaired.get(i).copied().unwrap_or("");
+        if old != new {
+            changes.push((i, old.to_string(), new.to_string()));
+        }
+    }
+    changes
+}
+
+/// most_likely_error_position returns the position of the word that is most likely to have been
+/// transcribed incorrectly in an invalid phrase. Every single-word correction that produces a
+/// valid phrase is found, and the position whose correction is closest to the original word by
+/// edit distance is returned, with ties going to the earlier position. None is returned if the
+/// phrase is already valid or if no single-word correction exists.
+pub fn most_likely_error_position(phrase: &str) -> Option<usize> {
+    // A valid phrase can still have single-word corrections that happen to match its checksum.
+    if valid_seed_phrase(phrase).is_ok() {
+        return None;
+    }
+    let words = phrase_words(phrase)?;
+    single_word_repairs(&words)
+        .into_iter()
+        .min_by_key(|&(i, word)| edit_distance(&words[i], word))
+        .map(|(i, _)| i)
+}
+
+/// has_adjacent_duplicates returns the position of every word that repeats the word before it.
+/// Valid phrases can repeat words, so this is only a hint: a phrase that fails its checksum and
+/// has an adjacent duplicate was likely transcribed with a word written twice. Words are compared
+/// by dictionary prefix, ignoring case.
+pub fn has_adjacent_duplicates(phrase: &str) -> Vec<usize> {
+    let words: Vec<String> = phrase
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect();
+    (1..words.len())
+        .filter(|&i| same_word(&words[i - 1], &words[i]))
+        .collect()
+}
+
+/// phrase_words lowercases a phrase and splits it into words, returning None if it does not have
+/// exactly 15 words or contains non-ascii characters.
+fn phrase_words(phrase: &str) -> Option<Vec<String>> {
+    if !phrase.is_ascii() {
+        return None;
+    }
+    let words: Vec<String> = phrase
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.len() != PHRASE_WORDS {
+        return None;
+    }
+    Some(words)
+}
+
+/// repair_seed_phrase_deadline searches for valid phrases that differ from an invalid phrase in
+/// one or two words, returning each candidate in canonical form. Phrases with one word replaced
+/// are all checked before any phrase with two words replaced. If the phrase is already valid, its
+/// canonical form is the only candidate.
+///
+/// A two-word search checks tens of millions of phrases and can take minutes, so the search stops
+/// once 'deadline' passes and returns the candidates found so far. The results may therefore be
+/// partial, and an empty result does not mean that no repair exists.
+pub fn repair_seed_phrase_deadline(phrase: &str, deadline: Instant) -> Vec<String> {
+    repair_deadline(phrase, "", deadline)
+}
+
+/// repair_seed_phrase_tagged_deadline is repair_seed_phrase_deadline for phrases created with
+/// seed_to_seed_phrase_tagged. The candidates are valid for the given tag. An error is returned
+/// if the tag is not valid.
+pub fn repair_seed_phrase_tagged_deadline(
+    phrase: &str,
+    tag: &str,
+    deadline: Instant,
+) -> Result<Vec<String>, Error> {
+    let tag = normalize_tag(tag)?;
+    Ok(repair_deadline(phrase, &tag, deadline))
+}
+
+/// repair_deadline implements repair_seed_phrase_deadline and
+/// repair_seed_phrase_tagged_deadline, computing checksums with the normalized 'tag'.
+fn repair_deadline(phrase: &str, tag: &str, deadline: Instant) -> Vec<String> {
+    let words = match phrase_words(phrase) {
+        Some(w) => w,
+        None => return Vec::new(),
+    };
+    let indices = word_indices(&words);
+    let to_phrase = |found: &[usize; PHRASE_WORDS]| -> String {
+        let words: Vec<&str> = found.iter().map(|&i| DICTIONARY[i]).collect();
+        words.join(" ")
+    };
+
+    let mut found = Vec::new();
+    if indices.iter().all(|i| i.is_some()) {
+        repairs_at(&indices, &[], tag, None, &mut found);
+        if !found.is_empty() {
+            return found.iter().map(to_phrase).collect();
+        }
+    }
+    'search: for count in 1..=2 {
+        for a in 0..PHRASE_WORDS {
+            let positions: Vec<Vec<usize>> = if count == 1 {
+                vec![vec![a]]
+            } else {
+                (a + 1..PHRASE_WORDS).map(|b| vec![a, b]).collect()
+            };
+            for positions in positions {
+                if !repairs_at(&indices, &positions, tag, Some(deadline), &mut found) {
+                    break 'search;
+                }
+            }
+        }
+    }
+    found.iter().map(to_phrase).collect()
+}
+
+/// estimate_repair_time estimates how long a full repair of 'phrase' would take on this machine,
+/// so that a UI can warn before starting a long search. A few thousand candidate phrases are
+/// checked and timed, and the time per candidate is multiplied by the number of candidates the
+/// search has to check. The estimate is approximate: it depends on the load of the machine while
+/// the samples run, and a search that finds its answer early finishes sooner. Phrases that can't
+/// be repaired because they don't have 15 ascii words, and phrases that are already valid, need
+/// no search and return zero.
+pub fn estimate_repair_time(phrase: &str, mode: RepairMode) -> Duration {
+    let words = match phrase_words(phrase) {
+        Some(w) => w,
+        None => return Duration::ZERO,
+    };
+    let indices = word_indices(&words);
+    if indices.iter().all(|i| i.is_some()) {
+        let mut found = Vec::new();
+        repairs_at(&indices, &[], "", None, &mut found);
+        if !found.is_empty() {
+            return Duration::ZERO;
+        }
+    }
+
+    let candidates = match mode {
+        RepairMode::OneWord => repair_search_space(1),
+        RepairMode::TwoWord => repair_search_space(1).saturating_add(repair_search_space(2)),
+    };
+    let start = Instant::now();
+    let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+    for i in 0..ESTIMATE_SAMPLES {
+        entropy[0] = i as usize % 1024;
+        if let Ok(seed) = entropy_indices_to_seed(black_box(&entropy)) {
+            black_box(checksum_word_indices_tagged(seed, ""));
+        }
+    }
+    let per_candidate = start.elapsed() / ESTIMATE_SAMPLES;
+    per_candidate.saturating_mul(candidates.min(u32::MAX as u64) as u32)
+}
+
+/// word_indices returns the dictionary index of each word, or None for words that aren't in the
+/// dictionary.
+fn word_indices(words: &[String]) -> Vec<Option<usize>> {
+    words
+        .iter()
+        .map(|w| index_of(w).map(|i| i as usize))
+        .collect()
+}
+
+/// single_word_repairs returns every (position, word) pair such that replacing the word at that
+/// position produces a valid phrase. If the phrase is already valid, nothing is returned.
+fn single_word_repairs(words: &[String]) -> Vec<(usize, &'static str)> {
+    let indices = word_indices(words);
+    let mut repairs = Vec::new();
+    for pos in 0..PHRASE_WORDS {
+        let mut found = Vec::new();
+        repairs_at(&indices, &[pos], "", None, &mut found);
+        repairs.extend(found.iter().map(|f| (pos, DICTIONARY[f[pos]])));
+    }
+    repairs
+}
+
+/// repairs_at finds every valid phrase that differs from 'indices' at exactly the given
+/// positions and nowhere else, appending the indices of each to 'found'. Every word that isn't in
+/// the dictionary must be one of the positions. Checksums are computed with the normalized 'tag'.
+/// False is returned if the deadline passed before the search completed.
+fn repairs_at(
+    indices: &[Option<usize>],
+    positions: &[usize],
+    tag: &str,
+    deadline: Option<Instant>,
+    found: &mut Vec<[usize; PHRASE_WORDS]>,
+) -> bool {
+    let mut current = [0usize; PHRASE_WORDS];
+    for (i, c) in current.iter_mut().enumerate() {
+        match indices[i] {
+            Some(index) => *c = index,
+            None if positions.contains(&i) => {}
+            None => return true,
+        }
+    }
+
+    // Only the entropy positions need to be searched, the checksum words at the remaining
+    // positions follow from the entropy.
+    let entropy_positions: Vec<usize> = positions
+        .iter()
+        .copied()
+        .filter(|&p| p < SEED_ENTROPY_WORDS)
+        .collect();
+    let limit = |p: usize| {
+        if p == SEED_ENTROPY_WORDS - 1 {
+            256
+        } else {
+            1024
+        }
+    };
+    let mut checked = 0u64;
+    let mut candidate = vec![0usize; entropy_positions.len()];
+    loop {
+        // Every position in the set must hold a different word than the original, otherwise
+        // the candidate would be found again by the search over a smaller set of positions.
+        let changed = entropy_positions
+            .iter()
+            .zip(&candidate)
+            .all(|(&p, &c)| indices[p] != Some(c));
+        if changed {
+            for (&p, &c) in entropy_positions.iter().zip(&candidate) {
+                current[p] = c;
+            }
+            let mut entropy = [0usize; SEED_ENTROPY_WORDS];
+            entropy.copy_from_slice(&current[..SEED_ENTROPY_WORDS]);
+            if let Ok(seed) = entropy_indices_to_seed(&entropy) {
+                let (c1, c2) = checksum_word_indices_tagged(seed, tag);
+                let matches = |p: usize, c: usize| {
+                    if positions.contains(&p) {
+                        indices[p] != Some(c)
+                    } else {
+                        indices[p] == Some(c)
+                    }
+                };
+                if matches(SEED_ENTROPY_WORDS, c1) && matches(SEED_ENTROPY_WORDS + 1, c2) {
+                    let mut repaired = current;
+                    repaired[SEED_ENTROPY_WORDS] = c1;
+                    repaired[SEED_ENTROPY_WORDS + 1] = c2;
+                    found.push(repaired);
+                }
+            }
+        }
+
+        checked += 1;
+        if checked.is_multiple_of(1024) && deadline.is_some_and(|d| Instant::now() >= d) {
+            return false;
+        }
+
+        // Advance to the next candidate, like an odometer.
+        let mut i = 0;
+        loop {
+            if i == candidate.len() {
+                return true;
+            }
+            candidate[i] += 1;
+            if candidate[i] < limit(entropy_positions[i]) {
+                break;
+            }
+            candidate[i] = 0;
+            i += 1;
+        }
+    }
+}
+
+/// edit_distance returns the Levenshtein distance between two ascii words.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = prev[j] + (ca != cb) as usize;
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::{seed_to_seed_phrase, seed_to_seed_phrase_tagged};
+    use crate::random_seed;
+
+    #[test]
+    // Compare corrupted phrases against the phrase they were corrupted from.
+    fn check_repair_changes() {
+        let repaired = seed_to_seed_phrase(random_seed());
+        let mut words: Vec<&str> = repaired.split(' ').collect();
+        assert!(repair_changes(&repaired, &repaired).is_empty());
+
+        let (old4, old11) = (words[4], words[11]);
+        words[4] = "helo";
+        words[11] = "zzz";
+        let original = words.join("  ");
+        assert_eq!(
+            repair_changes(&original, &repaired),
+            vec![
+                (4, "helo".to_string(), old4.to_string()),
+                (11, "zzz".to_string(), old11.to_string()),
+            ]
+        );
+
+        // Missing words are reported as empty.
+        let truncated = repaired.rsplit_once(' ').unwrap().0;
+        let last = repaired.rsplit_once(' ').unwrap().1;
+        assert_eq!(
+            repair_changes(truncated, &repaired),
+            vec![(14, String::new(), last.to_string())]
+        );
+    }
+
+    #[test]
+    // Corrupt one word with a typo and check that its position is identified.
+    fn check_most_likely_error_position() {
+        assert_eq!(edit_distance("hello", "helo"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        // Valid phrases have no error position, even when an unrelated correction happens to
+        // match the checksum.
+        for _ in 0..100 {
+            let phrase = seed_to_seed_phrase(random_seed());
+            assert_eq!(most_likely_error_position(&phrase), None);
+        }
+        let phrase = seed_to_seed_phrase(random_seed());
+        assert_eq!(most_likely_error_position("abbey"), None);
+
+        for pos in [0, 5, 12, 13, 14] {
+            // Swapping the first letter keeps the word close to the original, while the other
+            // single-word corrections are unrelated words.
+            let mut words: Vec<String> = phrase.split(' ').map(|w| w.to_string()).collect();
+            let first = if words[pos].starts_with('z') {
+                "y"
+            } else {
+                "z"
+            };
+            words[pos].replace_range(..1, first);
+            let corrupted = words.join(" ");
+            if valid_seed_phrase(&corrupted).is_ok() {
+                continue;
+            }
+            assert_eq!(
+                most_likely_error_position(&corrupted),
+                Some(pos),
+                "{}",
+                corrupted
+            );
+        }
+    }
+
+    #[test]
+    // Estimates are positive for invalid phrases and grow with the number of words searched.
+    fn check_estimate_repair_time() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        assert_eq!(
+            estimate_repair_time(&phrase, RepairMode::TwoWord),
+            Duration::ZERO
+        );
+        assert_eq!(
+            estimate_repair_time("abbey", RepairMode::TwoWord),
+            Duration::ZERO
+        );
+
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        words[3] = "zzz";
+        let broken = words.join(" ");
+        let one = estimate_repair_time(&broken, RepairMode::OneWord);
+        let two = estimate_repair_time(&broken, RepairMode::TwoWord);
+        assert!(one > Duration::ZERO);
+        assert!(two > one);
+    }
+
+    #[test]
+    // Duplicate a word and check that the duplicate is found.
+    fn check_has_adjacent_duplicates() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        let expected: Vec<usize> = (1..words.len())
+            .filter(|&i| words[i] == words[i - 1])
+            .collect();
+        assert_eq!(has_adjacent_duplicates(&phrase), expected);
+
+        // Writing a word twice pushes the rest of the phrase back by one.
+        words.insert(6, words[5]);
+        words.pop();
+        let duplicated = words.join(" ");
+        assert!(has_adjacent_duplicates(&duplicated).contains(&6));
+
+        // Prefixes and case don't hide a duplicate, and unknown words are never duplicates.
+        assert_eq!(has_adjacent_duplicates("abbey ABB able"), vec![1]);
+        assert!(has_adjacent_duplicates("zzz zzz").is_empty());
+        assert!(has_adjacent_duplicates("").is_empty());
+        assert!(has_adjacent_duplicates("caf\u{e9} caf\u{e9}").is_empty());
+    }
+
+    #[test]
+    // Repair one and two corrupted words, and check that a near deadline is respected.
+    fn check_repair_seed_phrase_deadline() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        let far = Instant::now() + std::time::Duration::from_secs(3600);
+        assert_eq!(
+            repair_seed_phrase_deadline(&phrase, far),
+            vec![phrase.clone()]
+        );
+        assert!(repair_seed_phrase_deadline("abbey", far).is_empty());
+
+        // A word that isn't in the dictionary can only be repaired at its own position, and
+        // single-word repairs are found before the two-word search starts.
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        words[5] = "zzz";
+        let corrupted = words.join(" ");
+        let soon = Instant::now() + std::time::Duration::from_millis(200);
+        assert_eq!(repair_seed_phrase_deadline(&corrupted, soon)[0], phrase);
+
+        // When two words are not in the dictionary, only that pair of positions is searched.
+        words[5] = phrase.split(' ').nth(5).unwrap();
+        words[12] = "zzz";
+        words[14] = "zzz";
+        let corrupted = words.join(" ");
+        let repairs = repair_seed_phrase_deadline(&corrupted, far);
+        assert!(repairs.contains(&phrase));
+
+        // A two-word search over the entropy words takes far longer than the deadline.
+        words[12] = phrase.split(' ').nth(12).unwrap();
+        words[14] = phrase.split(' ').nth(14).unwrap();
+        words[0] = "zzz";
+        words[1] = "zzz";
+        let corrupted = words.join(" ");
+        let start = Instant::now();
+        let deadline = start + std::time::Duration::from_millis(50);
+        repair_seed_phrase_deadline(&corrupted, deadline);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    // Tagged phrases are repaired with their own checksum.
+    fn check_repair_seed_phrase_tagged_deadline() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase_tagged(seed, "dv").unwrap();
+        let far = Instant::now() + std::time::Duration::from_secs(3600);
+        assert_eq!(
+            repair_seed_phrase_tagged_deadline(&phrase, "DV", far).unwrap(),
+            vec![phrase.clone()]
+        );
+        repair_seed_phrase_tagged_deadline(&phrase, "d v", far).unwrap_err();
+
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        words[3] = "zzz";
+        let corrupted = words.join(" ");
+        let soon = Instant::now() + std::time::Duration::from_millis(200);
+        let repairs = repair_seed_phrase_tagged_deadline(&corrupted, "dv", soon).unwrap();
+        assert_eq!(repairs[0], phrase);
+
+        // The untagged repair finds a different phrase or none at all.
+        let soon = Instant::now() + std::time::Duration::from_millis(200);
+        assert!(!repair_seed_phrase_deadline(&corrupted, soon).contains(&phrase));
+    }
+}
@@ -0,0 +1,127 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! session contains a helper for holding a seed in memory for a limited amount of time after the
+//! user enters their seed phrase.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::{Error, Result};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::phrase::seed_phrase_to_seed;
+use crate::Seed;
+
+/// SessionExpired is returned when a SeedSession is accessed after it has expired or been locked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionExpired;
+
+impl fmt::Display for SessionExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "seed session has expired")
+    }
+}
+
+impl std::error::Error for SessionExpired {}
+
+/// SeedSession holds a seed in memory until a deadline passes. Expiry is checked lazily each time
+/// the seed is accessed, there is no background thread. The seed is wiped as soon as an access
+/// finds the session expired, when lock() is called, or when the session is dropped.
+pub struct SeedSession {
+    seed: RefCell<Zeroizing<Seed>>,
+    expires: Option<Instant>,
+    locked: Cell<bool>,
+}
+
+impl SeedSession {
+    /// unlock parses the seed phrase and creates a session that holds the seed for 'ttl'. A ttl so
+    /// long that the deadline can't be represented, such as Duration::MAX, never expires, and the
+    /// session only ends when it is locked or dropped.
+    pub fn unlock(phrase: &str, ttl: Duration) -> Result<SeedSession, Error> {
+        let seed = Zeroizing::new(seed_phrase_to_seed(phrase)?);
+        Ok(SeedSession {
+            seed: RefCell::new(seed),
+            expires: Instant::now().checked_add(ttl),
+            locked: Cell::new(false),
+        })
+    }
+
+    /// with_seed calls 'f' with the seed if the session has not yet expired. If the session has
+    /// expired, the seed is wiped and an error is returned. 'f' receives a zeroizing copy of the
+    /// seed rather than a borrow of the session, so it may call lock().
+    pub fn with_seed<T>(&self, f: impl FnOnce(&Seed) -> T) -> Result<T, SessionExpired> {
+        if let Some(expires) = self.expires {
+            if Instant::now() >= expires {
+                self.lock();
+            }
+        }
+        if self.locked.get() {
+            return Err(SessionExpired);
+        }
+        let seed = Zeroizing::new(**self.seed.borrow());
+        Ok(f(&seed))
+    }
+
+    /// lock wipes the seed immediately. All future accesses will fail.
+    pub fn lock(&self) {
+        self.seed.borrow_mut().zeroize();
+        self.locked.set(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeroize::ZeroizeOnDrop;
+
+    use crate::phrase::seed_to_seed_phrase;
+    use crate::random_seed;
+
+    #[test]
+    // Check access before expiry, after expiry, and after an explicit lock.
+    fn check_seed_session() {
+        let seed = random_seed();
+        let phrase = seed_to_seed_phrase(seed);
+
+        // Access before expiry.
+        let session = SeedSession::unlock(&phrase, Duration::from_secs(60)).unwrap();
+        assert_eq!(session.with_seed(|s| *s).unwrap(), seed);
+
+        // Explicit lock wipes the seed.
+        session.lock();
+        assert_eq!(session.with_seed(|s| *s), Err(SessionExpired));
+        assert_eq!(**session.seed.borrow(), [0u8; 16]);
+
+        // Access after expiry wipes the seed.
+        let session = SeedSession::unlock(&phrase, Duration::from_millis(10)).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(session.with_seed(|s| *s), Err(SessionExpired));
+        assert_eq!(**session.seed.borrow(), [0u8; 16]);
+
+        // A ttl too long to represent never expires, and the closure may lock the session.
+        let session = SeedSession::unlock(&phrase, Duration::MAX).unwrap();
+        assert_eq!(session.with_seed(|s| *s).unwrap(), seed);
+        assert_eq!(session.with_seed(|_| session.lock()), Ok(()));
+        assert_eq!(session.with_seed(|s| *s), Err(SessionExpired));
+        assert_eq!(**session.seed.borrow(), [0u8; 16]);
+
+        // Invalid phrases can't unlock a session.
+        SeedSession::unlock("not a seed phrase", Duration::from_secs(60))
+            .err()
+            .unwrap();
+    }
+
+    #[test]
+    // The seed is held in a wrapper that wipes it when the session is dropped.
+    fn check_seed_session_drop() {
+        fn zeroizes_on_drop<T: ZeroizeOnDrop>(_: &T) {}
+        let phrase = seed_to_seed_phrase(random_seed());
+        let session = SeedSession::unlock(&phrase, Duration::from_secs(60)).unwrap();
+        zeroizes_on_drop(&*session.seed.borrow());
+        drop(session);
+    }
+}
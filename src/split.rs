@@ -0,0 +1,209 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! split contains helpers for splitting a seed into multiple pieces that can be stored in
+//! different locations.
+
+use anyhow::{bail, Error, Result};
+
+use crate::phrase::{seed_phrase_to_seed, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS};
+use crate::{random_seed, Seed};
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// xor_split splits a seed into two shares using a 2-of-2 XOR scheme. The first share is a random
+/// pad and the second share is the seed XORed with the pad, so each share on its own is uniformly
+/// random and reveals nothing about the seed. Both shares are needed to recover the seed. Each
+/// share is a valid seed and can be written down as its own seed phrase.
+pub fn xor_split(seed: Seed) -> (Seed, Seed) {
+    let pad = random_seed();
+    (pad, xor_combine(seed, pad))
+}
+
+/// xor_combine recovers a seed from the two shares produced by xor_split.
+pub fn xor_combine(a: Seed, b: Seed) -> Seed {
+    let mut seed: Seed = [0u8; 16];
+    for i in 0..seed.len() {
+        seed[i] = a[i] ^ b[i];
+    }
+    seed
+}
+
+/// phrase_to_cards splits a seed phrase across 'cards' printable cards, so that a backup can be
+/// stored in several places. Each card holds a consecutive run of words, and every word is written
+/// with its position, for example "card 2 of 3: 6.abbey 7.acid 8.acorn 9.acre 10.acts". The first
+/// cards get an extra word when 15 words don't divide evenly.
+///
+/// This is not a threshold scheme: every card is needed to recover the phrase, and each card
+/// reveals the words written on it. The split only spreads the phrase out physically. Use
+/// xor_split when the pieces should reveal nothing on their own. Between 1 and 15 cards can be
+/// made, and the phrase must have 15 words.
+pub fn phrase_to_cards(phrase: &str, cards: usize) -> Result<Vec<String>, Error> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if words.len() != PHRASE_WORDS {
+        bail!(
+            "expecting {} words but got {} words",
+            PHRASE_WORDS,
+            words.len()
+        );
+    }
+    if cards == 0 || cards > PHRASE_WORDS {
+        bail!("can only split a phrase into 1 to {} cards", PHRASE_WORDS);
+    }
+
+    let mut result = Vec::with_capacity(cards);
+    let mut next = 0;
+    for card in 0..cards {
+        let count = PHRASE_WORDS / cards + (card < PHRASE_WORDS % cards) as usize;
+        let entries: Vec<String> = (next..next + count)
+            .map(|i| format!("{}.{}", i + 1, words[i]))
+            .collect();
+        result.push(format!(
+            "card {} of {}: {}",
+            card + 1,
+            cards,
+            entries.join(" ")
+        ));
+        next += count;
+    }
+    Ok(result)
+}
+
+/// cards_to_phrase reassembles the cards produced by phrase_to_cards, in any order, and returns
+/// the phrase. An error is returned if a card is malformed, if any card is missing or repeated,
+/// or if the reassembled phrase is not valid.
+pub fn cards_to_phrase(cards: &[String]) -> Result<String, Error> {
+    let mut words: Vec<Option<String>> = vec![None; PHRASE_WORDS];
+    let mut seen: Vec<bool> = Vec::new();
+    for card in cards {
+        let (header, entries) = match card.trim().split_once(": ") {
+            Some(split) => split,
+            None => bail!("card '{}' is missing its header", card),
+        };
+        let numbers: Vec<&str> = header.split(' ').collect();
+        let (number, total) = match numbers.as_slice() {
+            ["card", number, "of", total] => (number.parse::<usize>()?, total.parse::<usize>()?),
+            _ => bail!("card header '{}' is malformed", header),
+        };
+        if total == 0 || total > PHRASE_WORDS {
+            bail!("card header '{}' has an invalid card count", header);
+        }
+        if seen.is_empty() {
+            seen = vec![false; total];
+        }
+        if total != seen.len() || number == 0 || number > total {
+            bail!("card header '{}' does not match the other cards", header);
+        }
+        if seen[number - 1] {
+            bail!("card {} appears more than once", number);
+        }
+        seen[number - 1] = true;
+
+        for entry in entries.split(' ') {
+            let (position, word) = match entry.split_once('.') {
+                Some(split) => split,
+                None => bail!("card entry '{}' is missing its position", entry),
+            };
+            let position = position.parse::<usize>()?;
+            if position == 0 || position > PHRASE_WORDS {
+                bail!("card entry '{}' has an invalid position", entry);
+            }
+            if words[position - 1].is_some() {
+                bail!("word {} appears more than once", position);
+            }
+            words[position - 1] = Some(word.to_string());
+        }
+    }
+    if let Some(missing) = seen.iter().position(|&s| !s) {
+        bail!("card {} is missing", missing + 1);
+    }
+
+    let mut phrase = Vec::with_capacity(PHRASE_WORDS);
+    for (i, word) in words.into_iter().enumerate() {
+        match word {
+            Some(word) => phrase.push(word),
+            None => bail!("word {} is missing", i + 1),
+        }
+    }
+    let phrase = phrase.join(" ");
+    seed_phrase_to_seed(&phrase)?;
+    Ok(phrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::seed_to_seed_phrase;
+
+    #[test]
+    // Check that xor shares round-trip through phrases and that a share is independent of the
+    // seed.
+    fn check_xor_split() {
+        let seed = random_seed();
+        let (a, b) = xor_split(seed);
+        assert_eq!(xor_combine(a, b), seed);
+        assert_eq!(xor_combine(b, a), seed);
+
+        // Each share can be stored as a phrase.
+        let a = seed_phrase_to_seed(&seed_to_seed_phrase(a)).unwrap();
+        let b = seed_phrase_to_seed(&seed_to_seed_phrase(b)).unwrap();
+        assert_eq!(xor_combine(a, b), seed);
+
+        // Splitting a structured seed should not produce structured shares, and splitting the
+        // same seed twice should produce unrelated shares.
+        let (a1, b1) = xor_split([0u8; 16]);
+        let (a2, b2) = xor_split([0u8; 16]);
+        assert_ne!(a1, [0u8; 16]);
+        assert_ne!(b1, [0u8; 16]);
+        assert_ne!(a1, a2);
+        assert_ne!(b1, b2);
+    }
+
+    #[test]
+    // Split a phrase across cards and reassemble it from the cards in any order.
+    fn check_phrase_to_cards() {
+        let phrase = seed_to_seed_phrase(random_seed());
+        let cards = phrase_to_cards(&phrase, 3).unwrap();
+        assert_eq!(cards.len(), 3);
+        assert!(cards[1].starts_with("card 2 of 3: 6."));
+        for card in &cards {
+            assert_eq!(card.split(' ').count(), 4 + 5);
+        }
+        assert_eq!(cards_to_phrase(&cards).unwrap(), phrase);
+        let reversed: Vec<String> = cards.iter().rev().cloned().collect();
+        assert_eq!(cards_to_phrase(&reversed).unwrap(), phrase);
+
+        // Uneven splits give the first cards an extra word.
+        for n in 1..=PHRASE_WORDS {
+            let cards = phrase_to_cards(&phrase, n).unwrap();
+            assert_eq!(cards.len(), n);
+            assert_eq!(cards_to_phrase(&cards).unwrap(), phrase);
+        }
+        let cards = phrase_to_cards(&phrase, 4).unwrap();
+        let counts: Vec<usize> = cards.iter().map(|c| c.split(' ').count() - 4).collect();
+        assert_eq!(counts, vec![4, 4, 4, 3]);
+
+        // Every card is needed, and each card can only be used once.
+        let cards = phrase_to_cards(&phrase, 3).unwrap();
+        cards_to_phrase(&cards[..2]).unwrap_err();
+        cards_to_phrase(&[cards[0].clone(), cards[0].clone(), cards[1].clone()]).unwrap_err();
+        let other = phrase_to_cards(&phrase, 2).unwrap();
+        cards_to_phrase(&[cards[0].clone(), other[1].clone()]).unwrap_err();
+        cards_to_phrase(&[]).unwrap_err();
+        let garbled = cards[2].replace(":", "");
+        cards_to_phrase(&[cards[0].clone(), cards[1].clone(), garbled]).unwrap_err();
+        for card in [
+            "card 1 of 18446744073709551615: 1.abbey",
+            "card 1 of 16: 1.abbey",
+            "card 1 of 0: 1.abbey",
+        ] {
+            cards_to_phrase(&[card.to_string()]).unwrap_err();
+        }
+
+        phrase_to_cards(&phrase, 0).unwrap_err();
+        phrase_to_cards(&phrase, 16).unwrap_err();
+        phrase_to_cards("abbey", 1).unwrap_err();
+    }
+}
@@ -0,0 +1,304 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! ssh contains helpers for using the keypair of a seed with OpenSSH, including exporting it in
+//! the OpenSSH key formats and producing the SSHSIG signatures that git uses for SSH commit
+//! signing.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{PublicKey, Signature, Signer};
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+use crate::keypair::keypair_from_seed_zeroizing;
+use crate::Seed;
+
+/// SSH_ED25519 is the ssh name of the ed25519 key and signature types.
+pub(crate) const SSH_ED25519: &[u8] = b"ssh-ed25519";
+
+/// SSHSIG_MAGIC starts both the signed data and the signature blob of an SSHSIG signature.
+const SSHSIG_MAGIC: &[u8] = b"SSHSIG";
+
+/// SSHSIG_HASH is the hash algorithm used for SSHSIG signatures.
+const SSHSIG_HASH: &[u8] = b"sha512";
+
+/// OPENSSH_KEY_MAGIC starts an unencrypted OpenSSH private key.
+const OPENSSH_KEY_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+/// ARMOR_WIDTH is the line width OpenSSH uses for base64 in armored files.
+const ARMOR_WIDTH: usize = 70;
+
+/// GitSigningSetup holds everything needed to configure git to sign commits with the keypair of
+/// a seed. The private key is wiped when the setup is dropped and is redacted from the Debug
+/// output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct GitSigningSetup {
+    /// private_key is the unencrypted OpenSSH private key, for the file named by
+    /// 'user.signingkey'.
+    pub private_key: Zeroizing<String>,
+    /// public_key is the OpenSSH public key line.
+    pub public_key: String,
+    /// allowed_signers is the line to add to the file named by 'gpg.ssh.allowedSignersFile'.
+    pub allowed_signers: String,
+}
+
+impl fmt::Debug for GitSigningSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitSigningSetup")
+            .field("private_key", &"<redacted>")
+            .field("public_key", &self.public_key)
+            .field("allowed_signers", &self.allowed_signers)
+            .finish()
+    }
+}
+
+/// put_string appends an ssh string, which is a big endian u32 length followed by the bytes.
+pub(crate) fn put_string(out: &mut Vec<u8>, s: &[u8]) {
+    out.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    out.extend_from_slice(s);
+}
+
+/// take_string reads an ssh string from the front of 'input', returning None if it is truncated.
+#[cfg(any(test, all(unix, feature = "ssh-agent")))]
+pub(crate) fn take_string<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    if input.len() < 4 {
+        return None;
+    }
+    let len = u32::from_be_bytes([input[0], input[1], input[2], input[3]]) as usize;
+    let rest = &input[4..];
+    if rest.len() < len {
+        return None;
+    }
+    let (s, rest) = rest.split_at(len);
+    *input = rest;
+    Some(s)
+}
+
+/// public_key_blob returns the ssh wire encoding of an ed25519 public key.
+pub(crate) fn public_key_blob(public: &PublicKey) -> Vec<u8> {
+    let mut blob = Vec::new();
+    put_string(&mut blob, SSH_ED25519);
+    put_string(&mut blob, public.as_bytes());
+    blob
+}
+
+/// signature_blob returns the ssh wire encoding of an ed25519 signature.
+pub(crate) fn signature_blob(signature: &Signature) -> Vec<u8> {
+    let mut blob = Vec::new();
+    put_string(&mut blob, SSH_ED25519);
+    put_string(&mut blob, &signature.to_bytes());
+    blob
+}
+
+/// armor wraps 'data' in base64 between BEGIN and END lines, like OpenSSH.
+fn armor(label: &str, data: &[u8]) -> Zeroizing<String> {
+    let encoded = Zeroizing::new(STANDARD.encode(data));
+    // Reserve the full length up front so that no partial copy of the data is left behind in a
+    // reallocated buffer.
+    let lines = encoded.len().div_ceil(ARMOR_WIDTH);
+    let mut armored = Zeroizing::new(String::with_capacity(
+        2 * label.len() + 32 + encoded.len() + lines,
+    ));
+    armored.push_str("-----BEGIN ");
+    armored.push_str(label);
+    armored.push_str("-----\n");
+    for line in encoded.as_bytes().chunks(ARMOR_WIDTH) {
+        armored.push_str(std::str::from_utf8(line).unwrap());
+        armored.push('\n');
+    }
+    armored.push_str("-----END ");
+    armored.push_str(label);
+    armored.push_str("-----\n");
+    armored
+}
+
+/// seed_to_openssh_public_key returns the OpenSSH public key line for the keypair of a seed, in
+/// the form used by authorized_keys files.
+pub fn seed_to_openssh_public_key(seed: Seed, comment: &str) -> String {
+    let keypair = keypair_from_seed_zeroizing(seed);
+    let blob = STANDARD.encode(public_key_blob(&keypair.public));
+    if comment.is_empty() {
+        format!("ssh-ed25519 {}", blob)
+    } else {
+        format!("ssh-ed25519 {} {}", blob, comment)
+    }
+}
+
+/// seed_to_openssh_private_key returns the keypair of a seed as an unencrypted OpenSSH private
+/// key. OpenSSH fills the two check integers with random bytes; here they are taken from the
+/// public key so that the same seed always produces the same file. The key is returned in a
+/// wrapper that wipes it when dropped.
+pub fn seed_to_openssh_private_key(seed: Seed, comment: &str) -> Zeroizing<String> {
+    let keypair = keypair_from_seed_zeroizing(seed);
+    let public = keypair.public.as_bytes();
+
+    let mut private = Zeroizing::new(Vec::new());
+    private.extend_from_slice(&public[..4]);
+    private.extend_from_slice(&public[..4]);
+    put_string(&mut private, SSH_ED25519);
+    put_string(&mut private, public);
+    put_string(&mut private, &Zeroizing::new(keypair.to_bytes())[..]);
+    put_string(&mut private, comment.as_bytes());
+    let mut pad = 1;
+    while private.len() % 8 != 0 {
+        private.push(pad);
+        pad += 1;
+    }
+
+    let mut key = Zeroizing::new(OPENSSH_KEY_MAGIC.to_vec());
+    put_string(&mut key, b"none");
+    put_string(&mut key, b"none");
+    put_string(&mut key, b"");
+    key.extend_from_slice(&1u32.to_be_bytes());
+    put_string(&mut key, &public_key_blob(&keypair.public));
+    put_string(&mut key, &private);
+    armor("OPENSSH PRIVATE KEY", &key)
+}
+
+/// git_signing_setup returns the files needed to sign git commits with the keypair of a seed,
+/// with the key identified by 'email' in the allowed signers file. The private key is not
+/// encrypted, so it should be written with restrictive permissions or loaded into an agent.
+pub fn git_signing_setup(seed: Seed, email: &str) -> GitSigningSetup {
+    let public_key = seed_to_openssh_public_key(seed, "");
+    GitSigningSetup {
+        private_key: seed_to_openssh_private_key(seed, email),
+        allowed_signers: format!("{} namespaces=\"git\" {}", email, public_key),
+        public_key,
+    }
+}
+
+/// sshsig_signed_data returns the data that is actually signed by an SSHSIG signature.
+fn sshsig_signed_data(namespace: &str, data: &[u8]) -> Vec<u8> {
+    let mut signed = SSHSIG_MAGIC.to_vec();
+    put_string(&mut signed, namespace.as_bytes());
+    put_string(&mut signed, b"");
+    put_string(&mut signed, SSHSIG_HASH);
+    put_string(&mut signed, &Sha512::digest(data));
+    signed
+}
+
+/// sign_git_data signs 'data' with the keypair of a seed and returns an armored SSHSIG signature,
+/// the format produced by 'ssh-keygen -Y sign'. Git signs commits with the namespace "git", and
+/// 'git verify-commit' accepts the result when the key is in the allowed signers file. The data is
+/// hashed with SHA-512.
+pub fn sign_git_data(seed: Seed, namespace: &str, data: &[u8]) -> String {
+    let keypair = keypair_from_seed_zeroizing(seed);
+    let signature = keypair.sign(&sshsig_signed_data(namespace, data));
+
+    let mut blob = SSHSIG_MAGIC.to_vec();
+    blob.extend_from_slice(&1u32.to_be_bytes());
+    put_string(&mut blob, &public_key_blob(&keypair.public));
+    put_string(&mut blob, namespace.as_bytes());
+    put_string(&mut blob, b"");
+    put_string(&mut blob, SSHSIG_HASH);
+    put_string(&mut blob, &signature_blob(&signature));
+    armor("SSH SIGNATURE", &blob).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair_from_seed;
+    use crate::random_seed;
+    use ed25519_dalek::Verifier;
+
+    // dearmor strips the armor lines and decodes the base64 between them.
+    fn dearmor(armored: &str, label: &str) -> Vec<u8> {
+        let lines: Vec<&str> = armored.lines().collect();
+        assert_eq!(lines[0], format!("-----BEGIN {}-----", label));
+        assert_eq!(lines[lines.len() - 1], format!("-----END {}-----", label));
+        for line in &lines[1..lines.len() - 1] {
+            assert!(line.len() <= ARMOR_WIDTH);
+        }
+        STANDARD.decode(lines[1..lines.len() - 1].concat()).unwrap()
+    }
+
+    #[test]
+    // Decode an SSHSIG signature field by field and verify it.
+    fn check_sign_git_data() {
+        let seed = random_seed();
+        let public = keypair_from_seed(seed).public;
+        let armored = sign_git_data(seed, "git", b"tree 1234\n");
+        assert_eq!(armored, sign_git_data(seed, "git", b"tree 1234\n"));
+
+        let blob = dearmor(&armored, "SSH SIGNATURE");
+        assert_eq!(&blob[..6], SSHSIG_MAGIC);
+        assert_eq!(blob[6..10], 1u32.to_be_bytes());
+        let mut fields = &blob[10..];
+        assert_eq!(take_string(&mut fields).unwrap(), public_key_blob(&public));
+        assert_eq!(take_string(&mut fields).unwrap(), b"git");
+        assert_eq!(take_string(&mut fields).unwrap(), b"");
+        assert_eq!(take_string(&mut fields).unwrap(), SSHSIG_HASH);
+        let mut encoded = take_string(&mut fields).unwrap();
+        assert!(fields.is_empty());
+        assert_eq!(take_string(&mut encoded).unwrap(), SSH_ED25519);
+        let signature = Signature::from_bytes(take_string(&mut encoded).unwrap()).unwrap();
+        assert!(encoded.is_empty());
+
+        public
+            .verify(&sshsig_signed_data("git", b"tree 1234\n"), &signature)
+            .unwrap();
+        public
+            .verify(&sshsig_signed_data("file", b"tree 1234\n"), &signature)
+            .unwrap_err();
+        public
+            .verify(&sshsig_signed_data("git", b"tree 1235\n"), &signature)
+            .unwrap_err();
+    }
+
+    #[test]
+    // Decode the exported keys and the allowed signers line.
+    fn check_git_signing_setup() {
+        let seed = random_seed();
+        let keypair = keypair_from_seed(seed);
+        let setup = git_signing_setup(seed, "user@example.com");
+        assert_eq!(setup, git_signing_setup(seed, "user@example.com"));
+
+        let blob = STANDARD.encode(public_key_blob(&keypair.public));
+        assert_eq!(setup.public_key, format!("ssh-ed25519 {}", blob));
+        assert_eq!(
+            setup.allowed_signers,
+            format!("user@example.com namespaces=\"git\" ssh-ed25519 {}", blob)
+        );
+        assert_eq!(
+            seed_to_openssh_public_key(seed, "laptop"),
+            format!("ssh-ed25519 {} laptop", blob)
+        );
+
+        // The private key is redacted from Debug output, and was armored without reallocating.
+        let debug = format!("{:?}", setup);
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("PRIVATE KEY"));
+        assert!(debug.contains(&setup.public_key));
+        assert_eq!(setup.private_key.len(), setup.private_key.capacity());
+
+        let key = dearmor(&setup.private_key, "OPENSSH PRIVATE KEY");
+        assert_eq!(&key[..15], OPENSSH_KEY_MAGIC);
+        let mut fields = &key[15..];
+        assert_eq!(take_string(&mut fields).unwrap(), b"none");
+        assert_eq!(take_string(&mut fields).unwrap(), b"none");
+        assert_eq!(take_string(&mut fields).unwrap(), b"");
+        assert_eq!(fields[..4], 1u32.to_be_bytes());
+        fields = &fields[4..];
+        assert_eq!(
+            take_string(&mut fields).unwrap(),
+            public_key_blob(&keypair.public)
+        );
+        let private = take_string(&mut fields).unwrap();
+        assert!(fields.is_empty());
+        assert_eq!(private.len() % 8, 0);
+        assert_eq!(private[..4], private[4..8]);
+        let mut fields = &private[8..];
+        assert_eq!(take_string(&mut fields).unwrap(), SSH_ED25519);
+        assert_eq!(take_string(&mut fields).unwrap(), keypair.public.as_bytes());
+        assert_eq!(take_string(&mut fields).unwrap(), keypair.to_bytes());
+        assert_eq!(take_string(&mut fields).unwrap(), b"user@example.com");
+        for (i, pad) in fields.iter().enumerate() {
+            assert_eq!(*pad as usize, i + 1);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! ssh_agent serves keys derived from a seed to local tools over the ssh-agent protocol, so that
+//! the private keys never have to be written to disk. Only the two requests needed for
+//! authentication and signing are supported: listing identities and signing data. Every other
+//! request, including adding and removing keys, receives SSH_AGENT_FAILURE. It is only available
+//! on unix with the 'ssh-agent' feature.
+
+use std::fs::Permissions;
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use anyhow::{bail, Error, Result};
+use ed25519_dalek::Signer;
+
+use crate::derive::device_seed;
+use crate::keypair::{keypair_from_seed_zeroizing, ZeroizingKeypair};
+use crate::ssh::{public_key_blob, put_string, signature_blob, take_string};
+use crate::Seed;
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// MAX_MESSAGE_LEN is the largest request the agent will read. Anything larger closes the
+/// connection, so a misbehaving client can't make the agent allocate unbounded memory.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// AgentIdentity describes a key served by the agent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AgentIdentity {
+    /// comment is shown by tools such as 'ssh-add -l'.
+    pub comment: String,
+    /// context selects the key. None serves the seed's own keypair, as returned by
+    /// keypair_from_seed. Some(name) serves the keypair of device_seed(seed, name).
+    pub context: Option<String>,
+}
+
+/// AgentKey is an identity along with its derived keypair.
+struct AgentKey {
+    comment: String,
+    keypair: ZeroizingKeypair,
+}
+
+/// AgentHandle controls a running agent. Dropping the handle stops the agent and removes its
+/// socket.
+#[derive(Debug)]
+pub struct AgentHandle {
+    socket_path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AgentHandle {
+    /// socket_path returns the path of the socket, which is the value to use for SSH_AUTH_SOCK.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// shutdown stops the agent and removes its socket. Connections that are already open are
+    /// served until the client closes them.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    /// stop implements shutdown and Drop.
+    fn stop(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return,
+        };
+        // Wake up the accept loop with a connection of our own so that it sees the flag.
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = UnixStream::connect(&self.socket_path);
+        let _ = thread.join();
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+impl Drop for AgentHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// serve_agent derives a keypair for each identity and serves them on a new unix socket at
+/// 'socket_path'. Signing happens in this process; clients only ever see the public keys and the
+/// signatures. Each connection is served on its own thread. An error is returned if the identity
+/// list is empty or the socket can't be created.
+///
+/// Anyone who can connect to the socket can use the keys, so the socket is made readable and
+/// writable only by its owner (mode 0600) before any connection is accepted. As with ssh-agent,
+/// the socket should still be placed in a directory that only the owner can enter, since another
+/// user could connect in the moment between creating the socket and changing its mode.
+pub fn serve_agent(
+    seed: Seed,
+    socket_path: &Path,
+    identities: &[AgentIdentity],
+) -> Result<AgentHandle, Error> {
+    if identities.is_empty() {
+        bail!("the agent needs at least one identity");
+    }
+    let keys = Arc::new(derive_keys(seed, identities));
+    let listener = UnixListener::bind(socket_path)?;
+    if let Err(e) = std::fs::set_permissions(socket_path, Permissions::from_mode(0o600)) {
+        let _ = std::fs::remove_file(socket_path);
+        bail!(
+            "unable to restrict the permissions of the agent socket: {}",
+            e
+        );
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread = std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if thread_stop.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Ok(stream) = stream {
+                let keys = keys.clone();
+                std::thread::spawn(move || handle_connection(stream, &keys));
+            }
+        }
+    });
+    Ok(AgentHandle {
+        socket_path: socket_path.to_path_buf(),
+        stop,
+        thread: Some(thread),
+    })
+}
+
+/// derive_keys derives the keypair of every identity.
+fn derive_keys(seed: Seed, identities: &[AgentIdentity]) -> Vec<AgentKey> {
+    identities
+        .iter()
+        .map(|identity| {
+            let key_seed = match &identity.context {
+                Some(context) => device_seed(seed, context),
+                None => seed,
+            };
+            AgentKey {
+                comment: identity.comment.clone(),
+                keypair: keypair_from_seed_zeroizing(key_seed),
+            }
+        })
+        .collect()
+}
+
+/// handle_connection answers requests on a single connection until the client disconnects or
+/// sends a malformed message.
+fn handle_connection(mut stream: UnixStream, keys: &[AgentKey]) {
+    loop {
+        let mut len = [0u8; 4];
+        if stream.read_exact(&mut len).is_err() {
+            return;
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 || len > MAX_MESSAGE_LEN {
+            return;
+        }
+        let mut request = vec![0u8; len];
+        if stream.read_exact(&mut request).is_err() {
+            return;
+        }
+        let response = respond(&request, keys);
+        let mut framed = (response.len() as u32).to_be_bytes().to_vec();
+        framed.extend_from_slice(&response);
+        if stream.write_all(&framed).is_err() {
+            return;
+        }
+    }
+}
+
+/// respond returns the response to a single request, without the length prefix.
+fn respond(request: &[u8], keys: &[AgentKey]) -> Vec<u8> {
+    match request[0] {
+        SSH_AGENTC_REQUEST_IDENTITIES if request.len() == 1 => {
+            let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+            response.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+            for key in keys {
+                put_string(&mut response, &public_key_blob(&key.keypair.public));
+                put_string(&mut response, key.comment.as_bytes());
+            }
+            response
+        }
+        SSH_AGENTC_SIGN_REQUEST => match sign_request(&request[1..], keys) {
+            Some(response) => response,
+            None => vec![SSH_AGENT_FAILURE],
+        },
+        _ => vec![SSH_AGENT_FAILURE],
+    }
+}
+
+/// sign_request answers a sign request, or returns None if the request is malformed or names a
+/// key that the agent doesn't hold. The flags are ignored because they only select RSA signature
+/// algorithms.
+fn sign_request(mut body: &[u8], keys: &[AgentKey]) -> Option<Vec<u8>> {
+    let blob = take_string(&mut body)?;
+    let data = take_string(&mut body)?;
+    if body.len() != 4 {
+        return None;
+    }
+    let key = keys
+        .iter()
+        .find(|k| public_key_blob(&k.keypair.public) == blob)?;
+    let signature = key.keypair.sign(data);
+
+    let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+    put_string(&mut response, &signature_blob(&signature));
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::keypair_from_seed;
+    use crate::random_seed;
+    use crate::ssh::SSH_ED25519;
+    use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+    // request sends a framed request and returns the response, without its length prefix.
+    fn request(stream: &mut UnixStream, body: &[u8]) -> Vec<u8> {
+        let mut framed = (body.len() as u32).to_be_bytes().to_vec();
+        framed.extend_from_slice(body);
+        stream.write_all(&framed).unwrap();
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).unwrap();
+        let mut response = vec![0u8; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut response).unwrap();
+        response
+    }
+
+    // list_and_sign lists the identities over the stream and has each one sign a message,
+    // checking the signatures against the expected public keys.
+    fn list_and_sign(stream: &mut UnixStream, expected: &[(PublicKey, &str)]) {
+        let response = request(stream, &[SSH_AGENTC_REQUEST_IDENTITIES]);
+        assert_eq!(response[0], SSH_AGENT_IDENTITIES_ANSWER);
+        let mut body = &response[5..];
+        assert_eq!(response[1..5], (expected.len() as u32).to_be_bytes());
+        for (public, comment) in expected {
+            let blob = take_string(&mut body).unwrap();
+            assert_eq!(take_string(&mut body).unwrap(), comment.as_bytes());
+            let mut fields = blob;
+            assert_eq!(take_string(&mut fields).unwrap(), SSH_ED25519);
+            assert_eq!(take_string(&mut fields).unwrap(), public.as_bytes());
+
+            let mut sign = vec![SSH_AGENTC_SIGN_REQUEST];
+            put_string(&mut sign, blob);
+            put_string(&mut sign, b"data to sign");
+            sign.extend_from_slice(&0u32.to_be_bytes());
+            let response = request(stream, &sign);
+            assert_eq!(response[0], SSH_AGENT_SIGN_RESPONSE);
+            let mut body = &response[1..];
+            let mut encoded = take_string(&mut body).unwrap();
+            assert!(body.is_empty());
+            assert_eq!(take_string(&mut encoded).unwrap(), SSH_ED25519);
+            let signature = Signature::from_bytes(take_string(&mut encoded).unwrap()).unwrap();
+            public.verify(b"data to sign", &signature).unwrap();
+        }
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    // Speak the agent protocol over a socketpair.
+    fn check_agent_protocol() {
+        let seed = random_seed();
+        let identities = [
+            AgentIdentity {
+                comment: "main".to_string(),
+                context: None,
+            },
+            AgentIdentity {
+                comment: "laptop".to_string(),
+                context: Some("laptop".to_string()),
+            },
+        ];
+        let keys = derive_keys(seed, &identities);
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let thread = std::thread::spawn(move || handle_connection(server, &keys));
+
+        let main = keypair_from_seed(seed).public;
+        let laptop = keypair_from_seed(device_seed(seed, "laptop")).public;
+        list_and_sign(&mut client, &[(main, "main"), (laptop, "laptop")]);
+
+        // Unknown keys, malformed requests, and unsupported requests all fail.
+        let mut sign = vec![SSH_AGENTC_SIGN_REQUEST];
+        let other = keypair_from_seed_zeroizing(random_seed());
+        put_string(&mut sign, &public_key_blob(&other.public));
+        put_string(&mut sign, b"data");
+        sign.extend_from_slice(&0u32.to_be_bytes());
+        assert_eq!(request(&mut client, &sign), [SSH_AGENT_FAILURE]);
+        assert_eq!(request(&mut client, &sign[..10]), [SSH_AGENT_FAILURE]);
+        assert_eq!(request(&mut client, &[17]), [SSH_AGENT_FAILURE]);
+        assert_eq!(request(&mut client, &[11, 0]), [SSH_AGENT_FAILURE]);
+
+        // An oversized message closes the connection.
+        client
+            .write_all(&(MAX_MESSAGE_LEN as u32 + 1).to_be_bytes())
+            .unwrap();
+        thread.join().unwrap();
+    }
+
+    #[test]
+    // Serve the agent on a real socket and shut it down.
+    fn check_serve_agent() {
+        let seed = random_seed();
+        let path = std::env::temp_dir().join(format!("seed15-agent-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        serve_agent(seed, &path, &[]).unwrap_err();
+
+        let identities = [AgentIdentity {
+            comment: "main".to_string(),
+            context: None,
+        }];
+        let agent = serve_agent(seed, &path, &identities).unwrap();
+        assert_eq!(agent.socket_path(), path);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mut client = UnixStream::connect(&path).unwrap();
+        list_and_sign(&mut client, &[(keypair_from_seed(seed).public, "main")]);
+        drop(client);
+
+        agent.shutdown();
+        assert!(!path.exists());
+        UnixStream::connect(&path).unwrap_err();
+    }
+}
@@ -0,0 +1,160 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! strategies contains proptest strategies for seeds and seed phrases, for use in property tests
+//! of code that builds on seed15. It is only available with the 'proptest' feature.
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use seed15::phrase::seed_phrase_to_seed;
+//! use seed15::strategies::{arb_malformed_input, arb_valid_phrase};
+//!
+//! proptest! {
+//!     fn valid_phrases_parse(phrase in arb_valid_phrase()) {
+//!         prop_assert!(seed_phrase_to_seed(&phrase).is_ok());
+//!     }
+//!
+//!     fn malformed_input_does_not_panic(input in arb_malformed_input()) {
+//!         let _ = seed_phrase_to_seed(&input);
+//!     }
+//! }
+//!
+//! valid_phrases_parse();
+//! malformed_input_does_not_panic();
+//! ```
+
+use proptest::prelude::*;
+use proptest::sample::subsequence;
+
+use crate::phrase::{seed_to_seed_phrase, SEED_CHECKSUM_WORDS, SEED_ENTROPY_WORDS};
+use crate::Seed;
+use dictionary_1024::DICTIONARY;
+
+const PHRASE_WORDS: usize = SEED_ENTROPY_WORDS + SEED_CHECKSUM_WORDS;
+
+/// arb_seed generates uniformly random seeds.
+pub fn arb_seed() -> impl Strategy<Value = Seed> {
+    any::<Seed>()
+}
+
+/// arb_valid_phrase generates valid seed phrases in canonical form.
+pub fn arb_valid_phrase() -> impl Strategy<Value = String> {
+    arb_seed().prop_map(seed_to_seed_phrase)
+}
+
+/// arb_phrase_with_errors generates a seed along with its phrase after replacing between zero and
+/// 'max_errors' of the words with different dictionary words. Each corrupted position holds a word
+/// that differs from the original; the phrase will almost always fail its checksum, but with
+/// probability around one in a million it remains valid for a different seed. A 'max_errors'
+/// larger than 15 is treated as 15.
+pub fn arb_phrase_with_errors(max_errors: usize) -> impl Strategy<Value = (Seed, String)> {
+    let max_errors = max_errors.min(PHRASE_WORDS);
+    let positions = subsequence((0..PHRASE_WORDS).collect::<Vec<usize>>(), 0..=max_errors);
+    let offsets = proptest::collection::vec(1..DICTIONARY.len(), max_errors);
+    (arb_seed(), positions, offsets).prop_map(|(seed, positions, offsets)| {
+        let phrase = seed_to_seed_phrase(seed);
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        for (&pos, offset) in positions.iter().zip(offsets) {
+            let index = DICTIONARY.iter().position(|w| *w == words[pos]).unwrap();
+            words[pos] = DICTIONARY[(index + offset) % DICTIONARY.len()];
+        }
+        (seed, words.join(" "))
+    })
+}
+
+/// arb_malformed_input generates strings that are biased toward the shapes that trip up phrase
+/// parsers: valid phrases with altered whitespace, case, or word counts, phrases containing
+/// multi-byte characters, and arbitrary unicode strings.
+pub fn arb_malformed_input() -> impl Strategy<Value = String> {
+    let whitespace = prop_oneof![
+        Just(" ".to_string()),
+        Just("  ".to_string()),
+        Just("\t".to_string()),
+        Just("\n".to_string()),
+        Just("\u{a0}".to_string()),
+        Just(String::new()),
+    ];
+    prop_oneof![
+        // Words joined by assorted whitespace, with optional leading and trailing whitespace.
+        (
+            arb_valid_phrase(),
+            whitespace.clone(),
+            whitespace.clone(),
+            whitespace
+        )
+            .prop_map(|(phrase, sep, lead, trail)| {
+                format!("{}{}{}", lead, phrase.replace(' ', &sep), trail)
+            }),
+        // Uppercased phrases.
+        arb_valid_phrase().prop_map(|phrase| phrase.to_uppercase()),
+        // Too few or too many words.
+        (arb_valid_phrase(), 0..PHRASE_WORDS * 2).prop_map(|(phrase, count)| {
+            let words: Vec<&str> = phrase.split(' ').cycle().take(count).collect();
+            words.join(" ")
+        }),
+        // Multi-byte characters spliced into a word.
+        (
+            arb_valid_phrase(),
+            0..PHRASE_WORDS,
+            0..4usize,
+            any::<char>()
+        )
+            .prop_map(|(phrase, pos, offset, c)| {
+                let mut words: Vec<String> = phrase.split(' ').map(|w| w.to_string()).collect();
+                let offset = offset.min(words[pos].len());
+                words[pos].insert(offset, c);
+                words.join(" ")
+            }),
+        // Arbitrary strings.
+        any::<String>(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::{seed_phrase_to_seed, valid_seed_phrase, valid_seed_phrase_ct};
+    use crate::repair::repair_changes;
+
+    proptest! {
+        #[test]
+        // Every seed round-trips through its phrase.
+        fn check_arb_seed(seed in arb_seed()) {
+            prop_assert_eq!(seed_phrase_to_seed(&seed_to_seed_phrase(seed)).unwrap(), seed);
+        }
+
+        #[test]
+        // Valid phrases pass both validators.
+        fn check_arb_valid_phrase(phrase in arb_valid_phrase()) {
+            prop_assert!(valid_seed_phrase(&phrase).is_ok());
+            prop_assert!(valid_seed_phrase_ct(&phrase));
+        }
+
+        #[test]
+        // Corrupted phrases differ from the original in at most 'max_errors' words, and the
+        // validators agree on them.
+        fn check_arb_phrase_with_errors((seed, phrase) in arb_phrase_with_errors(3)) {
+            let changes = repair_changes(&phrase, &seed_to_seed_phrase(seed));
+            prop_assert!(changes.len() <= 3);
+            if changes.is_empty() {
+                prop_assert_eq!(seed_phrase_to_seed(&phrase).unwrap(), seed);
+            }
+            prop_assert_eq!(valid_seed_phrase_ct(&phrase), valid_seed_phrase(&phrase).is_ok());
+        }
+
+        #[test]
+        // A max_errors larger than the phrase is clamped rather than panicking.
+        fn check_arb_phrase_with_errors_clamped((seed, phrase) in arb_phrase_with_errors(100)) {
+            let changes = repair_changes(&phrase, &seed_to_seed_phrase(seed));
+            prop_assert!(changes.len() <= PHRASE_WORDS);
+        }
+
+        #[test]
+        // Malformed input never panics and the validators agree on it.
+        fn check_arb_malformed_input(input in arb_malformed_input()) {
+            prop_assert_eq!(valid_seed_phrase_ct(&input), valid_seed_phrase(&input).is_ok());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! testutils contains deterministic helpers for downstream test suites: an rng that yields a
+//! programmed byte sequence and a set of stable seed and phrase fixtures. It is only available with
+//! the 'test-utils' feature.
+//!
+//! The fixtures are published values and will never change. Downstream tests may hard-code them.
+
+use rand_core::{impls, CryptoRng, Error, RngCore};
+
+use crate::Seed;
+
+/// FIXTURE_COUNT is the number of available seed and phrase fixtures.
+pub const FIXTURE_COUNT: u8 = 10;
+
+const SEED_FIXTURES: [Seed; FIXTURE_COUNT as usize] = [
+    // 0: all zero bytes
+    [0x00; 16],
+    // 1: 0x01 repeated
+    [0x01; 16],
+    // 2: 0xff repeated (all bits set)
+    [0xff; 16],
+    // 3: 0xaa repeated (alternating bits, high bit set)
+    [0xaa; 16],
+    // 4: 0x55 repeated (alternating bits, low bit set)
+    [0x55; 16],
+    // 5: the bytes 0 through 15 in order
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    // 6: the seed used by the original phrase tests
+    [185, 46, 7, 1, 254, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    // 7: only the first bit set
+    [0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    // 8: only the last bit set
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01],
+    // 9: 0x0f repeated
+    [0x0f; 16],
+];
+
+const PHRASE_FIXTURES: [&str; FIXTURE_COUNT as usize] = [
+    "abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey donut bevel",
+    "abyss afar arise either abyss afar arise either abyss afar arise either able smog devoid",
+    "yank yank yank yank yank yank yank yank yank yank yank yank egg giant befit",
+    "pest pest pest pest pest pest pest pest pest pest pest pest clue rewind foe",
+    "future future future future future future future future future future future future avoid public wrap",
+    "abbey afar boss rope afoot bad issue acumen also cog room emit aerial riot aztec",
+    "recipe ray iron legion across abbey abbey abbey abbey abbey abbey abbey abbey timber wife",
+    "lesson abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey duty uncle",
+    "abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey able lag viking",
+    "arch dust veer run arch dust veer run arch dust veer run aerial vexed saga",
+];
+
+/// seed_fixture returns a stable seed fixture. The fixtures are:
+///
+/// - 0: all zero bytes
+/// - 1: 0x01 repeated
+/// - 2: 0xff repeated
+/// - 3: 0xaa repeated
+/// - 4: 0x55 repeated
+/// - 5: the bytes 0 through 15 in order
+/// - 6: [185, 46, 7, 1, 254, 2] followed by zeros
+/// - 7: only the first bit set
+/// - 8: only the last bit set
+/// - 9: 0x0f repeated
+///
+/// seed_fixture will panic if 'n' is not less than FIXTURE_COUNT.
+pub fn seed_fixture(n: u8) -> Seed {
+    if n >= FIXTURE_COUNT {
+        panic!(
+            "seed fixture {} does not exist, there are only {}",
+            n, FIXTURE_COUNT
+        );
+    }
+    SEED_FIXTURES[n as usize]
+}
+
+/// phrase_fixture returns the seed phrase for seed_fixture(n). It will panic if 'n' is not less
+/// than FIXTURE_COUNT.
+pub fn phrase_fixture(n: u8) -> &'static str {
+    if n >= FIXTURE_COUNT {
+        panic!(
+            "phrase fixture {} does not exist, there are only {}",
+            n, FIXTURE_COUNT
+        );
+    }
+    PHRASE_FIXTURES[n as usize]
+}
+
+/// FixedRng is a deterministic rng that yields a programmed sequence of bytes. It implements
+/// CryptoRng so that it can be passed to functions that require a secure rng, and must only ever
+/// be used in tests. FixedRng will panic if more bytes are requested than were programmed.
+pub struct FixedRng {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl FixedRng {
+    /// new creates a FixedRng that will yield 'bytes' in order.
+    pub fn new(bytes: &[u8]) -> FixedRng {
+        FixedRng {
+            bytes: bytes.to_vec(),
+            pos: 0,
+        }
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("{}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        let end = self.pos + dest.len();
+        if end > self.bytes.len() {
+            return Err(Error::new(format!(
+                "FixedRng exhausted: requested {} bytes but only {} remain",
+                dest.len(),
+                self.bytes.len() - self.pos
+            )));
+        }
+        dest.copy_from_slice(&self.bytes[self.pos..end]);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phrase::{seed_phrase_to_seed, seed_to_seed_phrase};
+
+    #[test]
+    // The fixtures are published and must never change. Check each phrase against its seed and
+    // pin a few of the values directly.
+    fn check_fixtures() {
+        for n in 0..FIXTURE_COUNT {
+            assert_eq!(seed_to_seed_phrase(seed_fixture(n)), phrase_fixture(n));
+            assert_eq!(
+                seed_phrase_to_seed(phrase_fixture(n)).unwrap(),
+                seed_fixture(n)
+            );
+        }
+        assert_eq!(seed_fixture(0), [0u8; 16]);
+        assert_eq!(seed_fixture(1), [1u8; 16]);
+        assert_eq!(
+            phrase_fixture(0),
+            "abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey abbey donut bevel"
+        );
+    }
+
+    #[test]
+    // Check that FixedRng yields its programmed bytes and then refuses to yield more.
+    fn check_fixed_rng() {
+        let mut rng = FixedRng::new(&[1, 2, 3, 4, 5, 6]);
+        let mut buf = [0u8; 2];
+        rng.fill_bytes(&mut buf);
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(rng.next_u32(), u32::from_le_bytes([3, 4, 5, 6]));
+        rng.try_fill_bytes(&mut buf).unwrap_err();
+    }
+}
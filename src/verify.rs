@@ -0,0 +1,189 @@
+#![forbid(unsafe_code)]
+#![deny(missing_docs)]
+#![deny(unused_must_use)]
+#![deny(unused_mut)]
+
+//! verify contains a self-check of the bit packing used to convert between seeds and seed
+//! phrases. It is intended to be run by compliance tooling, for example at startup in debug
+//! builds, and is only available with the 'verify' feature.
+
+use std::fmt;
+
+use crate::dictionary::lookup_index;
+use crate::phrase::{
+    checksum_indices_from_hash, seed_phrase_to_seed, seed_to_seed_phrase, SEED_ENTROPY_WORDS,
+};
+use crate::Seed;
+
+/// PackingViolation describes a single failed invariant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackingViolation {
+    /// check is the name of the invariant that failed.
+    pub check: &'static str,
+    /// detail describes the input that triggered the failure and what went wrong.
+    pub detail: String,
+}
+
+impl fmt::Display for PackingViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.detail)
+    }
+}
+
+/// verify_packing_invariants checks the seed phrase bit packing against a set of invariants:
+///
+/// - every bit of a walking-one seed appears in exactly one entropy word, at the expected
+///   position within that word
+/// - the 13th word never exceeds index 255 for any of the 256 values of its bits
+/// - the checksum words depend on exactly the first 20 bits of the seed hash
+/// - structured seeds (all zero, all one, alternating bits) round-trip
+///
+/// All violations are returned, not just the first.
+pub fn verify_packing_invariants() -> Result<(), Vec<PackingViolation>> {
+    let mut violations = Vec::new();
+    check_walking_ones(&mut violations);
+    check_final_word_bound(&mut violations);
+    check_checksum_bits(&mut violations);
+    check_round_trips(&mut violations);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// entropy_indices returns the dictionary indices of the entropy words of a seed's phrase.
+fn entropy_indices(seed: Seed) -> Result<[usize; SEED_ENTROPY_WORDS], String> {
+    let phrase = seed_to_seed_phrase(seed);
+    let mut indices = [0usize; SEED_ENTROPY_WORDS];
+    for (index, word) in indices.iter_mut().zip(phrase.split(' ')) {
+        *index = lookup_index(word).map_err(|e| e.to_string())?;
+    }
+    Ok(indices)
+}
+
+/// check_walking_ones verifies that each seed bit lands in exactly one word.
+fn check_walking_ones(violations: &mut Vec<PackingViolation>) {
+    for bit in 0..128 {
+        let mut seed: Seed = [0u8; 16];
+        seed[bit / 8] = 1 << (7 - bit % 8);
+        let indices = match entropy_indices(seed) {
+            Ok(i) => i,
+            Err(e) => {
+                violations.push(PackingViolation {
+                    check: "walking_one",
+                    detail: format!("bit {}: {}", bit, e),
+                });
+                continue;
+            }
+        };
+
+        // Every word holds 10 bits except the 13th, which holds the final 8.
+        let word = bit / 10;
+        let bits_in_word = if word == SEED_ENTROPY_WORDS - 1 {
+            8
+        } else {
+            10
+        };
+        let expected = 1usize << (bits_in_word - 1 - bit % 10);
+        for (i, &index) in indices.iter().enumerate() {
+            let want = if i == word { expected } else { 0 };
+            if index != want {
+                violations.push(PackingViolation {
+                    check: "walking_one",
+                    detail: format!(
+                        "bit {}: word {} has index {:#b}, expected {:#b}",
+                        bit, i, index, want
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// check_final_word_bound verifies that the 13th word stays within the first 256 words of the
+/// dictionary for every value of its 8 bits.
+fn check_final_word_bound(violations: &mut Vec<PackingViolation>) {
+    for value in 0..=255u8 {
+        for fill in [0x00u8, 0xff] {
+            let mut seed: Seed = [fill; 16];
+            seed[15] = value;
+            let index = match entropy_indices(seed) {
+                Ok(i) => i[SEED_ENTROPY_WORDS - 1],
+                Err(e) => {
+                    violations.push(PackingViolation {
+                        check: "final_word_bound",
+                        detail: format!("final byte {:#04x}: {}", value, e),
+                    });
+                    continue;
+                }
+            };
+            if index != value as usize {
+                violations.push(PackingViolation {
+                    check: "final_word_bound",
+                    detail: format!(
+                        "final byte {:#04x} with fill {:#04x} produced 13th word index {}",
+                        value, fill, index
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// check_checksum_bits flips each bit of a checksum hash and verifies that the checksum words
+/// change exactly when one of the first 20 bits is flipped.
+fn check_checksum_bits(violations: &mut Vec<PackingViolation>) {
+    for base in [[0x00u8; 32], [0xffu8; 32], [0x5au8; 32]] {
+        let original = checksum_indices_from_hash(&base);
+        for bit in 0..256 {
+            let mut hash = base;
+            hash[bit / 8] ^= 1 << (7 - bit % 8);
+            let changed = checksum_indices_from_hash(&hash) != original;
+            if changed != (bit < 20) {
+                violations.push(PackingViolation {
+                    check: "checksum_bits",
+                    detail: format!(
+                        "flipping hash bit {} of {:#04x} pattern changed checksum: {}",
+                        bit, base[0], changed
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// check_round_trips verifies that structured seeds survive conversion to a phrase and back.
+fn check_round_trips(violations: &mut Vec<PackingViolation>) {
+    for byte in [0x00u8, 0xff, 0xaa, 0x55] {
+        let seed: Seed = [byte; 16];
+        let phrase = seed_to_seed_phrase(seed);
+        match seed_phrase_to_seed(&phrase) {
+            Ok(s) if s == seed => {}
+            Ok(s) => violations.push(PackingViolation {
+                check: "round_trip",
+                detail: format!("seed {:?} round-tripped to {:?}", seed, s),
+            }),
+            Err(e) => violations.push(PackingViolation {
+                check: "round_trip",
+                detail: format!(
+                    "seed {:?} produced invalid phrase '{}': {}",
+                    seed, phrase, e
+                ),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // The packing code should satisfy all of its invariants.
+    fn check_verify_packing_invariants() {
+        if let Err(violations) = verify_packing_invariants() {
+            panic!("packing violations: {:#?}", violations);
+        }
+    }
+}